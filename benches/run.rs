@@ -0,0 +1,106 @@
+// Copyright 2020-2021, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Measures throughput and allocations of `Pattern::run` for inputs of
+//! different sizes, run with `cargo bench`.
+
+use simd_json::{BorrowedValue, OwnedValue};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use tremor_kv::{Pattern, Trim};
+
+/// Counts the allocations made through the global allocator
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        BYTES.fetch_add(new_size, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const ITERATIONS: u32 = 20_000;
+
+fn input(pairs: usize) -> String {
+    (0..pairs)
+        .map(|i| format!("key{i}={}", if i % 2 == 0 { "value" } else { "42" }))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn bench<F: Fn()>(name: &str, bytes: usize, f: F) {
+    f();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let allocated = BYTES.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+    let allocated = BYTES.load(Ordering::Relaxed) - allocated;
+    let per_run = elapsed / ITERATIONS;
+    let mb_s = (bytes as f64 * f64::from(ITERATIONS)) / elapsed.as_secs_f64() / 1_000_000.0;
+    println!(
+        "{name:<32} {:>10.2?}/run {mb_s:>8.1} MB/s {:>6} allocs/run {:>8} bytes/run",
+        per_run.max(Duration::from_nanos(1)),
+        allocations / ITERATIONS as usize,
+        allocated / ITERATIONS as usize,
+    );
+}
+
+fn main() {
+    let plain = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+    let coerced = plain.clone().with_coerce_values(true);
+    let quoted = plain.clone().with_quotes(&['"']);
+    let trimmed = plain.clone().with_trim_value(Trim::Whitespace);
+    for (size, pairs) in [("small", 4), ("medium", 32), ("large", 512)] {
+        let input = input(pairs);
+        let bytes = input.len();
+        bench(&format!("{size} borrowed"), bytes, || {
+            black_box(plain.run::<BorrowedValue>(black_box(&input)));
+        });
+        bench(&format!("{size} owned"), bytes, || {
+            black_box(plain.run::<OwnedValue>(black_box(&input)));
+        });
+        bench(&format!("{size} borrowed coerced"), bytes, || {
+            black_box(coerced.run::<BorrowedValue>(black_box(&input)));
+        });
+        bench(&format!("{size} borrowed quoted"), bytes, || {
+            black_box(quoted.run::<BorrowedValue>(black_box(&input)));
+        });
+        bench(&format!("{size} borrowed trimmed"), bytes, || {
+            black_box(trimmed.run::<BorrowedValue>(black_box(&input)));
+        });
+    }
+}
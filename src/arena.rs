@@ -0,0 +1,79 @@
+// Copyright 2020-2021, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Parsing into a bump arena
+
+use crate::{Pair, Pattern};
+use bumpalo::collections::Vec as BumpVec;
+use bumpalo::Bump;
+use std::collections::HashMap;
+
+impl Pattern {
+    /// Splits a string that represents KV pairs into a list of pairs whose
+    /// keys and values are allocated in `arena`, so many parses can share
+    /// one arena that is reset as a whole.
+    ///
+    /// The output borrows the arena and not the input, it lives until the
+    /// arena is reset or dropped. Values are not coerced and later values
+    /// of a repeated key overwrite earlier ones in place.
+    pub fn run_in_arena<'arena>(
+        &self,
+        input: &str,
+        arena: &'arena Bump,
+    ) -> Option<BumpVec<'arena, (&'arena str, &'arena str)>> {
+        let pairs = self.extract(input);
+        if pairs.is_empty() {
+            return None;
+        }
+        let mut r: BumpVec<(&str, &str)> = BumpVec::with_capacity_in(pairs.len(), arena);
+        let mut index: HashMap<&str, usize> = HashMap::with_capacity(pairs.len());
+        for Pair { key, val, .. } in pairs {
+            let val = arena.alloc_str(&val);
+            if let Some(&i) = index.get(&*key) {
+                r[i].1 = val;
+            } else {
+                let key = arena.alloc_str(&key);
+                index.insert(key, r.len());
+                r.push((key, val));
+            }
+        }
+        Some(r)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn run_in_arena() {
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        let mut arena = Bump::new();
+        for _ in 0..3 {
+            {
+                let r1 = kv
+                    .run_in_arena("a=1 b=2 a=3", &arena)
+                    .expect("Failed to split input");
+                let r2 = kv
+                    .run_in_arena(&String::from("c=4"), &arena)
+                    .expect("Failed to split input");
+                assert_eq!(&r1[..], &[("a", "3"), ("b", "2")]);
+                assert_eq!(&r2[..], &[("c", "4")]);
+                assert!(kv.run_in_arena("nothing", &arena).is_none());
+            }
+            assert!(arena.allocated_bytes() > 0);
+            arena.reset();
+        }
+    }
+}
@@ -0,0 +1,188 @@
+// Copyright 2020-2021, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Parsing batches of lines with a result per line
+
+use crate::{Output, ParseError, Pattern};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use std::iter;
+
+/// The outcome of parsing a single line of a batch
+#[derive(Debug, Clone, PartialEq)]
+pub enum BatchResult<V> {
+    /// the line was split into a value
+    Parsed(V),
+    /// the line is empty or consists of whitespace only
+    Empty,
+    /// the line has content but no pairs could be extracted from it
+    Failed,
+}
+
+impl<V> BatchResult<V> {
+    /// Returns the parsed value if there is one
+    pub fn parsed(self) -> Option<V> {
+        match self {
+            Self::Parsed(v) => Some(v),
+            Self::Empty | Self::Failed => None,
+        }
+    }
+}
+
+impl Pattern {
+    /// Splits each of the lines like `run`, the results are in the order of
+    /// the lines.
+    pub fn run_batch<'input, V>(&self, lines: &[&'input str]) -> Vec<Option<V>>
+    where
+        V: Output<'input>,
+    {
+        lines.iter().map(|line| self.run(line)).collect()
+    }
+
+    /// Splits each of the lines like `run_batch` but in parallel, the
+    /// results are in the order of the lines.
+    #[cfg(feature = "rayon")]
+    pub fn par_run_batch<'input, V>(&self, lines: &[&'input str]) -> Vec<Option<V>>
+    where
+        V: Output<'input> + Send,
+    {
+        lines.par_iter().map(|line| self.run(line)).collect()
+    }
+
+    /// Splits each of the lines like `run`, telling apart lines that are
+    /// empty from lines that could not be parsed.
+    pub fn run_batch_results<'input, V>(&self, lines: &[&'input str]) -> Vec<BatchResult<V>>
+    where
+        V: Output<'input>,
+    {
+        lines
+            .iter()
+            .map(|line| {
+                if line.trim().is_empty() {
+                    BatchResult::Empty
+                } else {
+                    self.run(line)
+                        .map_or(BatchResult::Failed, BatchResult::Parsed)
+                }
+            })
+            .collect()
+    }
+
+    /// Splits the input into records at the record separator and each of
+    /// them like `try_run`, together with the line the record starts on,
+    /// counting from 1. A trailing separator does not start another record
+    /// and with the default separator a `\r` before it is removed as well.
+    pub fn run_lines<'input, V>(
+        &'input self,
+        input: &'input str,
+    ) -> impl Iterator<Item = (usize, Result<V, ParseError<'input>>)> + 'input
+    where
+        V: Output<'input>,
+    {
+        let sep = self.record_separator.as_str();
+        let sep_lines = sep.matches('\n').count();
+        let mut rest = (!input.is_empty()).then_some(input);
+        let mut line = 1;
+        iter::from_fn(move || {
+            let current = rest?;
+            let record =
+                if let Some((record, tail)) = current.split_once(sep).filter(|_| !sep.is_empty()) {
+                    rest = (!tail.is_empty()).then_some(tail);
+                    record
+                } else {
+                    rest = None;
+                    current
+                };
+            let start = line;
+            line += record.matches('\n').count() + sep_lines;
+            let record = if sep == "\n" {
+                record.strip_suffix('\r').unwrap_or(record)
+            } else {
+                record
+            };
+            Some((start, self.try_run(record)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use simd_json::BorrowedValue;
+
+    #[test]
+    fn run_batch() {
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        let lines: Vec<String> = (0..100).map(|i| format!("i={i} x=y")).collect();
+        let mut lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+        lines.push("garbage");
+        let rs = kv.run_batch::<BorrowedValue>(&lines);
+        assert_eq!(rs.len(), 101);
+        for (i, r) in rs.iter().take(100).enumerate() {
+            let r = r.as_ref().expect("Failed to split input");
+            assert_eq!(r["i"], i.to_string().as_str());
+        }
+        assert_eq!(rs[100], None);
+        #[cfg(feature = "rayon")]
+        assert_eq!(kv.par_run_batch::<BorrowedValue>(&lines), rs);
+    }
+
+    #[test]
+    fn run_lines() {
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        let rs: Vec<_> = kv
+            .run_lines::<BorrowedValue>("a=1\r\n\ngarbage\nb=2\n")
+            .collect();
+        assert_eq!(rs.len(), 4);
+        assert_eq!(rs[0], (1, Ok(simd_json::json!({"a": "1"}).into())));
+        assert_eq!(rs[1], (2, Err(ParseError::Empty)));
+        assert_eq!(rs[2].0, 3);
+        assert!(rs[2].1.is_err());
+        assert_eq!(rs[3], (4, Ok(simd_json::json!({"b": "2"}).into())));
+
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_record_separator("\n--\n");
+        let rs: Vec<_> = kv
+            .run_lines::<BorrowedValue>("a=1\n--\nb=2\n--\nc=3")
+            .map(|(line, r)| (line, r.ok()))
+            .collect();
+        assert_eq!(
+            rs,
+            vec![
+                (1, Some(simd_json::json!({"a": "1"}).into())),
+                (3, Some(simd_json::json!({"b": "2"}).into())),
+                (5, Some(simd_json::json!({"c": "3"}).into()))
+            ]
+        );
+        assert_eq!(kv.run_lines::<BorrowedValue>("").count(), 0);
+    }
+
+    #[test]
+    fn batch_results() {
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        let mut rs = kv
+            .run_batch_results::<BorrowedValue>(&["a=1 b=2", "  ", "garbage"])
+            .into_iter();
+        let r = rs
+            .next()
+            .and_then(BatchResult::parsed)
+            .expect("Failed to split input");
+        assert_eq!(r["a"], "1");
+        assert_eq!(r["b"], "2");
+        assert_eq!(rs.next(), Some(BatchResult::Empty));
+        assert_eq!(rs.next(), Some(BatchResult::Failed));
+        assert_eq!(rs.next(), None);
+    }
+}
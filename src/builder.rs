@@ -0,0 +1,190 @@
+// Copyright 2020-2021, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Building patterns option by option instead of from a pattern string
+
+#[cfg(feature = "unicode-normalization")]
+use crate::NormForm;
+use crate::{
+    CoercionFallback, DuplicatePolicy, EmptyKeyPolicy, EmptyValuePolicy, Error, ExpectedType,
+    FlagPolicy, IndexGapPolicy, Pattern, SplitStrategy, Trim, Utf8Policy,
+};
+
+/// Builds a `Pattern`, created with `Pattern::builder`.
+///
+/// Field and key seperators default to a space and `:` like with
+/// `Pattern::compile` if none are given.
+#[derive(Debug, Clone, Default)]
+pub struct PatternBuilder {
+    field_seperators: Vec<String>,
+    key_seperators: Vec<String>,
+    quotes: Vec<char>,
+    pattern: Pattern,
+}
+
+macro_rules! options {
+    ($($(#[$attr:meta])* $name:ident($($arg:ident: $ty:ty),*) => $setter:ident;)*) => {
+        $(
+            $(#[$attr])*
+            #[doc = concat!("Sets the option of `Pattern::", stringify!($setter), "`")]
+            #[must_use]
+            pub fn $name(mut self, $($arg: $ty),*) -> Self {
+                self.pattern = self.pattern.$setter($($arg),*);
+                self
+            }
+        )*
+    };
+}
+
+impl Pattern {
+    /// Starts building a pattern
+    #[must_use]
+    pub fn builder() -> PatternBuilder {
+        PatternBuilder::default()
+    }
+}
+
+impl PatternBuilder {
+    /// Adds a field seperator
+    #[must_use]
+    pub fn field_separator(mut self, separator: &str) -> Self {
+        self.field_seperators.push(separator.to_string());
+        self
+    }
+
+    /// Adds a key seperator
+    #[must_use]
+    pub fn key_separator(mut self, separator: &str) -> Self {
+        self.key_seperators.push(separator.to_string());
+        self
+    }
+
+    /// Adds a quote character
+    #[must_use]
+    pub fn quote(mut self, quote: char) -> Self {
+        self.quotes.push(quote);
+        self
+    }
+
+    options! {
+        keep_quotes(keep: bool) => with_keep_quotes;
+        max_distinct_keys(max: usize) => with_max_distinct_keys;
+        coerce_values(coerce: bool) => with_coerce_values;
+        expected_type(key: &str, expected: ExpectedType) => with_expected_type;
+        coercion_fallback(fallback: CoercionFallback) => with_coercion_fallback;
+        collapse_consecutive_separators(collapse: bool) => with_collapse_consecutive_separators;
+        positional_keys(keys: &[&str]) => with_positional_keys;
+        include_keys(keys: &[&str]) => with_include_keys;
+        exclude_keys(keys: &[&str]) => with_exclude_keys;
+        trim_key(trim: Trim) => with_trim_key;
+        trim_value(trim: Trim) => with_trim_value;
+        key_prefix(prefix: &str) => with_key_prefix;
+        leading_token_key(key: &str) => with_leading_token_key;
+        respect_brackets(respect: bool) => with_respect_brackets;
+        strip_brackets(strip: bool) => with_strip_brackets;
+        whitespace_field_separator(whitespace: bool) => with_whitespace_field_separator;
+        strip_trailing_separator(strip: bool) => with_strip_trailing_separator;
+        split_once(once: bool) => with_split_once;
+        strict_logfmt(strict: bool) => with_strict_logfmt;
+        strict(strict: bool) => with_strict;
+        percent_decode(decode: bool) => with_percent_decode;
+        record_separator(separator: &str) => with_record_separator;
+        max_pairs(max: usize) => with_max_pairs;
+        max_input_length(max: usize) => with_max_input_length;
+        max_fields(max: usize) => with_max_fields;
+        max_key_length(max: usize) => with_max_key_length;
+        max_value_length(max: usize) => with_max_value_length;
+        invalid_utf8(policy: Utf8Policy) => with_invalid_utf8;
+        flag_policy(policy: FlagPolicy) => with_flag_policy;
+        empty_values(policy: EmptyValuePolicy) => with_empty_values;
+        empty_keys(policy: EmptyKeyPolicy) => with_empty_keys;
+        strip_control_chars(strip: bool) => with_strip_control_chars;
+        strip_control_chars_in_keys(strip: bool) => with_strip_control_chars_in_keys;
+        nested_values(nested: bool) => with_nested_values;
+        escape_char(escape: char) => with_escape_char;
+        then(inner: Pattern) => then;
+        trim_first_field(trim: bool) => with_trim_first_field;
+        nesting_separator(separator: &str) => with_nesting_separator;
+        nested_keys(nested: bool) => with_nested_keys;
+        array_index_notation(arrays: bool) => with_array_index_notation;
+        index_gaps(gaps: IndexGapPolicy) => with_index_gaps;
+        dedupe_identical(dedupe: bool) => with_dedupe_identical;
+        whole_as_value_key(key: &str) => with_whole_as_value_key;
+        max_total_depth(depth: usize) => with_max_total_depth;
+        recursive(recursive: bool) => with_recursive;
+        max_recursion_depth(depth: usize) => with_max_recursion_depth;
+        #[cfg(feature = "chrono")]
+        coerce_timestamps(coerce: bool) => with_coerce_timestamps;
+        duplicates(duplicates: DuplicatePolicy) => with_duplicates;
+        value_array_separator(separator: &str) => with_value_array_separator;
+        single_value_arrays(wrap: bool) => with_single_value_arrays;
+        value_separator_mapping(from: &str, to: &str) => with_value_separator_mapping;
+        #[cfg(feature = "unicode-normalization")]
+        normalize(form: NormForm) => with_normalize;
+        split_strategy(strategy: SplitStrategy) => with_split_strategy;
+    }
+
+    /// Builds the pattern
+    ///
+    /// # Errors
+    /// if a seperator is contained in another one, like with
+    /// `Pattern::compile`
+    pub fn build(self) -> Result<Pattern, Error> {
+        let seperators = Pattern::from_seperators(self.field_seperators, self.key_seperators)?;
+        let mut pattern = Pattern {
+            field_seperators: seperators.field_seperators,
+            key_seperators: seperators.key_seperators,
+            ..self.pattern
+        };
+        if !self.quotes.is_empty() {
+            pattern = pattern.with_quotes(&self.quotes);
+        }
+        Ok(pattern)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use simd_json::OwnedValue;
+
+    #[test]
+    fn builder() {
+        let kv = Pattern::builder()
+            .field_separator(";")
+            .key_separator("=")
+            .quote('"')
+            .coerce_values(true)
+            .build()
+            .expect("Failed to build pattern");
+        assert_eq!(
+            kv,
+            Pattern::compile("%{key}=%{val};")
+                .expect("Failed to build pattern")
+                .with_quotes(&['"'])
+                .with_coerce_values(true)
+        );
+        let r: OwnedValue = kv.run(r#"a=1;b="x;y""#).expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"a": 1, "b": "x;y"}));
+
+        assert_eq!(Pattern::builder().build(), Ok(Pattern::default()));
+        assert_eq!(
+            Pattern::builder()
+                .field_separator("=")
+                .key_separator("=")
+                .build(),
+            Err(Error::DoubleSeperator("=".to_string()))
+        );
+    }
+}
@@ -0,0 +1,131 @@
+// Copyright 2020-2021, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Splitting byte input that might not be valid UTF-8
+
+use crate::value::{InvalidValue, Obj};
+use crate::{Depth, Error, Output, Pattern, Utf8Policy};
+use std::ops::Range;
+
+impl Pattern {
+    /// Splits bytes that represent KV pairs like `run_checked`, invalid
+    /// UTF-8 is handled according to the pattern's `Utf8Policy`.
+    ///
+    /// Valid UTF-8 is split without copying, otherwise the output does not
+    /// borrow the input.
+    ///
+    /// # Errors
+    /// if the input is not valid UTF-8 with `Utf8Policy::Error`, with the
+    /// byte offset of the first invalid sequence, if values are nested
+    /// deeper than the maximum total depth or the input exceeds a limit
+    pub fn run_bytes<'input, V>(&self, input: &'input [u8]) -> Result<Option<V>, Error>
+    where
+        V: Output<'input>,
+    {
+        let e = match std::str::from_utf8(input) {
+            Ok(s) => return self.run_checked(s),
+            Err(e) => e,
+        };
+        if self.invalid_utf8 == Utf8Policy::Error {
+            return Err(Error::InvalidUtf8(e.valid_up_to()));
+        }
+        let (text, replaced) = lossy(input);
+        let mut pairs = self.extract_checked(&text, self.budget())?;
+        if self.invalid_utf8 == Utf8Policy::SkipField {
+            pairs.retain(|p| {
+                !is_replaced(&text, &replaced, &p.key) && !is_replaced(&text, &replaced, &p.val)
+            });
+        }
+        match self.obj(pairs, Depth::top(self)) {
+            Ok(obj) => Ok(obj.map(Obj::into_owned).and_then(Obj::into_value)),
+            Err(InvalidValue::Depth) => Err(Error::MaxDepthExceeded(self.depth_limit())),
+            Err(InvalidValue::Type) => Ok(None),
+        }
+    }
+}
+
+/// Replaces invalid UTF-8 with replacement characters and returns where
+/// they were put
+fn lossy(mut input: &[u8]) -> (String, Vec<Range<usize>>) {
+    let mut text = String::with_capacity(input.len());
+    let mut replaced = Vec::new();
+    loop {
+        match std::str::from_utf8(input) {
+            Ok(s) => {
+                text.push_str(s);
+                return (text, replaced);
+            }
+            Err(e) => {
+                let (valid, rest) = input.split_at(e.valid_up_to());
+                // the prefix was just checked
+                text.push_str(std::str::from_utf8(valid).unwrap_or_default());
+                let start = text.len();
+                text.push(char::REPLACEMENT_CHARACTER);
+                replaced.push(start..text.len());
+                input = &rest[e.error_len().unwrap_or(rest.len())..];
+            }
+        }
+    }
+}
+
+/// If `s` contains a character that replaced invalid UTF-8, for strings
+/// that don't borrow `text` any replacement character counts
+fn is_replaced(text: &str, replaced: &[Range<usize>], s: &str) -> bool {
+    let base = text.as_ptr() as usize;
+    let start = s.as_ptr() as usize;
+    if start < base || start + s.len() > base + text.len() {
+        return s.contains(char::REPLACEMENT_CHARACTER);
+    }
+    let span = start - base..start - base + s.len();
+    replaced
+        .iter()
+        .any(|r| r.start < span.end && span.start < r.end)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use simd_json::OwnedValue;
+
+    #[test]
+    fn run_bytes() {
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        let input = b"a=1 b=x\xffy c=\xe2\x9c\x93 d=\xef\xbf\xbd";
+        assert_eq!(
+            kv.run_bytes::<OwnedValue>(b"a=1 b=2"),
+            Ok(Some(simd_json::json!({"a": "1", "b": "2"})))
+        );
+        assert_eq!(
+            kv.run_bytes::<OwnedValue>(input),
+            Err(Error::InvalidUtf8(7))
+        );
+
+        let kv = kv.with_invalid_utf8(Utf8Policy::Lossy);
+        assert_eq!(
+            kv.run_bytes::<OwnedValue>(input),
+            Ok(Some(
+                simd_json::json!({"a": "1", "b": "x\u{fffd}y", "c": "✓", "d": "\u{fffd}"})
+            ))
+        );
+
+        let kv = kv.with_invalid_utf8(Utf8Policy::SkipField);
+        assert_eq!(
+            kv.run_bytes::<OwnedValue>(input),
+            Ok(Some(
+                simd_json::json!({"a": "1", "c": "✓", "d": "\u{fffd}"})
+            ))
+        );
+        assert_eq!(kv.run_bytes::<OwnedValue>(b"\xc3"), Ok(None));
+    }
+}
@@ -0,0 +1,62 @@
+// Copyright 2020-2021, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Streaming parse results through a channel
+
+use crate::Pattern;
+use simd_json::OwnedValue;
+use std::sync::mpsc::SyncSender;
+
+impl Pattern {
+    /// Splits every line of the input like `run` and sends the results
+    /// through `sender` in order, blocking while a bounded channel is full.
+    ///
+    /// Stops at the first line that can not be sent because the receiver
+    /// was dropped, returns `false` in that case and `true` once all lines
+    /// are sent.
+    pub fn run_lines_channel(&self, input: &str, sender: &SyncSender<Option<OwnedValue>>) -> bool {
+        input
+            .lines()
+            .all(|line| sender.send(self.run::<OwnedValue>(line)).is_ok())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::mpsc::sync_channel;
+    use std::thread;
+
+    #[test]
+    fn run_lines_channel() {
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        let (tx, rx) = sync_channel(1);
+        let producer = thread::spawn(move || kv.run_lines_channel("a=1\ngarbage\nb=2", &tx));
+        let rs: Vec<_> = rx.iter().collect();
+        assert!(producer.join().expect("producer panicked"));
+        assert_eq!(
+            rs,
+            vec![
+                Some(simd_json::json!({"a": "1"})),
+                None,
+                Some(simd_json::json!({"b": "2"}))
+            ]
+        );
+
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        let (tx, rx) = sync_channel(1);
+        drop(rx);
+        assert!(!kv.run_lines_channel("a=1\nb=2", &tx));
+    }
+}
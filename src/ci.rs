@@ -0,0 +1,127 @@
+// Copyright 2020-2021, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Case insensitive access to parsed outputs
+
+use crate::{Pair, Pattern};
+use std::collections::HashMap;
+
+/// A map from keys to values whose lookups ignore the case of keys while
+/// the original casing of keys is preserved
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CiMap {
+    /// `(original key, value)` in insertion order
+    entries: Vec<(String, String)>,
+    /// lower cased keys to their entry
+    index: HashMap<String, usize>,
+}
+
+impl CiMap {
+    /// Creates an empty map
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts a value, keys that only differ in case from an existing key
+    /// overwrite its value and keep its casing
+    pub fn insert(&mut self, key: &str, val: &str) {
+        let lower = key.to_lowercase();
+        if let Some(&i) = self.index.get(&lower) {
+            self.entries[i].1 = val.to_string();
+        } else {
+            self.index.insert(lower, self.entries.len());
+            self.entries.push((key.to_string(), val.to_string()));
+        }
+    }
+
+    /// Returns the value of `key` ignoring its case
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.index
+            .get(&key.to_lowercase())
+            .map(|&i| self.entries[i].1.as_str())
+    }
+
+    /// Returns the key with its original casing and the value of `key`
+    /// ignoring its case
+    pub fn get_key_value(&self, key: &str) -> Option<(&str, &str)> {
+        self.index.get(&key.to_lowercase()).map(|&i| {
+            let (k, v) = &self.entries[i];
+            (k.as_str(), v.as_str())
+        })
+    }
+
+    /// Checks if `key` is present ignoring its case
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.index.contains_key(&key.to_lowercase())
+    }
+
+    /// The keys with their original casing and the values in insertion
+    /// order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// The number of keys
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Checks if the map has no keys
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl Pattern {
+    /// Splits a string that represents KV pairs into a map whose lookups
+    /// ignore the case of keys.
+    ///
+    /// Values are not coerced, later values of a repeated key overwrite
+    /// earlier ones.
+    pub fn run_ci(&self, input: &str) -> Option<CiMap> {
+        let pairs = self.extract(input);
+        if pairs.is_empty() {
+            return None;
+        }
+        let mut r = CiMap::new();
+        for Pair { key, val, .. } in pairs {
+            r.insert(&key, &val);
+        }
+        Some(r)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn run_ci() {
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        let r = kv
+            .run_ci("Host=a User-Agent=curl HOST=b")
+            .expect("Failed to split input");
+        assert_eq!(r.len(), 2);
+        assert_eq!(r.get("host"), Some("b"));
+        assert_eq!(r.get("user-agent"), Some("curl"));
+        assert_eq!(r.get_key_value("HoSt"), Some(("Host", "b")));
+        assert!(r.contains_key("USER-AGENT"));
+        assert!(!r.contains_key("agent"));
+        assert_eq!(
+            r.iter().collect::<Vec<_>>(),
+            [("Host", "b"), ("User-Agent", "curl")]
+        );
+        assert!(kv.run_ci("nothing").is_none());
+    }
+}
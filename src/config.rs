@@ -0,0 +1,397 @@
+// Copyright 2020-2021, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Declarative pattern configuration, e.g. for codecs configured from YAML
+
+#[cfg(feature = "unicode-normalization")]
+use crate::NormForm;
+use crate::{
+    CoercionFallback, DuplicatePolicy, EmptyKeyPolicy, EmptyValuePolicy, Error, ExpectedType,
+    FlagPolicy, IndexGapPolicy, Pattern, PatternBuilder, SplitStrategy, Trim, Utf8Policy,
+};
+#[cfg(feature = "regex")]
+use crate::{RegexSeperator, Seperators, DEFAULT_REGEX_SIZE_LIMIT};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The options of a `Pattern` as plain data, turned into a pattern with
+/// `Pattern::from_config`.
+///
+/// Every field is optional when deserializing, missing fields keep the
+/// defaults of `Pattern::builder`. Unset options (`None`) are not applied.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct PatternConfig {
+    /// the field seperators, a space if empty
+    pub field_separators: Vec<String>,
+    /// the key seperators, `:` if empty
+    pub key_separators: Vec<String>,
+    /// see `Pattern::with_quotes`
+    pub quotes: Vec<char>,
+    /// see `Pattern::with_keep_quotes`
+    pub keep_quotes: bool,
+    /// see `Pattern::with_max_distinct_keys`
+    pub max_distinct_keys: Option<usize>,
+    /// see `Pattern::with_coerce_values`
+    pub coerce_values: bool,
+    /// see `Pattern::with_expected_type`
+    pub expected_types: BTreeMap<String, ExpectedType>,
+    /// see `Pattern::with_coercion_fallback`
+    pub coercion_fallback: CoercionFallback,
+    /// see `Pattern::with_collapse_consecutive_separators`
+    pub collapse_consecutive_separators: bool,
+    /// see `Pattern::with_positional_keys`
+    pub positional_keys: Vec<String>,
+    /// see `Pattern::with_include_keys`
+    pub include_keys: Vec<String>,
+    /// see `Pattern::with_exclude_keys`
+    pub exclude_keys: Vec<String>,
+    /// see `Pattern::with_trim_key`
+    pub trim_key: Option<Trim>,
+    /// see `Pattern::with_trim_value`
+    pub trim_value: Option<Trim>,
+    /// see `Pattern::with_key_prefix`
+    pub key_prefix: Option<String>,
+    /// see `Pattern::with_leading_token_key`
+    pub leading_token_key: Option<String>,
+    /// see `Pattern::with_respect_brackets`
+    pub respect_brackets: bool,
+    /// see `Pattern::with_strip_brackets`
+    pub strip_brackets: bool,
+    /// see `Pattern::with_whitespace_field_separator`
+    pub whitespace_field_separator: bool,
+    /// see `Pattern::with_strip_trailing_separator`
+    pub strip_trailing_separator: bool,
+    /// see `Pattern::with_split_once`
+    pub split_once: bool,
+    /// see `Pattern::with_strict_logfmt`
+    pub strict_logfmt: bool,
+    /// see `Pattern::with_strict`
+    pub strict: bool,
+    /// see `Pattern::with_percent_decode`
+    pub percent_decode: bool,
+    /// see `Pattern::with_max_pairs`
+    pub max_pairs: Option<usize>,
+    /// see `Pattern::with_max_input_length`
+    pub max_input_length: Option<usize>,
+    /// see `Pattern::with_max_fields`
+    pub max_fields: Option<usize>,
+    /// see `Pattern::with_max_key_length`
+    pub max_key_length: Option<usize>,
+    /// see `Pattern::with_max_value_length`
+    pub max_value_length: Option<usize>,
+    /// see `Pattern::with_record_separator`
+    pub record_separator: Option<String>,
+    /// see `Pattern::with_invalid_utf8`
+    pub invalid_utf8: Utf8Policy,
+    /// see `Pattern::with_flag_policy`
+    pub flag_policy: FlagPolicy,
+    /// see `Pattern::with_empty_values`
+    pub empty_values: EmptyValuePolicy,
+    /// see `Pattern::with_empty_keys`
+    pub empty_keys: EmptyKeyPolicy,
+    /// see `Pattern::with_strip_control_chars`
+    pub strip_control_chars: bool,
+    /// see `Pattern::with_strip_control_chars_in_keys`
+    pub strip_control_chars_in_keys: bool,
+    /// see `Pattern::with_nested_values`
+    pub nested_values: bool,
+    /// see `Pattern::with_escape_char`
+    pub escape_char: Option<char>,
+    /// see `Pattern::then`
+    pub then: Option<Box<PatternConfig>>,
+    /// see `Pattern::with_trim_first_field`
+    pub trim_first_field: bool,
+    /// see `Pattern::with_nested_keys`, `nesting_separator` takes precedence
+    pub nested_keys: bool,
+    /// see `Pattern::with_nesting_separator`
+    pub nesting_separator: Option<String>,
+    /// see `Pattern::with_array_index_notation`
+    pub array_index_notation: bool,
+    /// see `Pattern::with_index_gaps`
+    pub index_gaps: IndexGapPolicy,
+    /// see `Pattern::with_dedupe_identical`
+    pub dedupe_identical: bool,
+    /// see `Pattern::with_whole_as_value_key`
+    pub whole_as_value_key: Option<String>,
+    /// see `Pattern::with_max_total_depth`
+    pub max_total_depth: Option<usize>,
+    /// see `Pattern::with_recursive`
+    pub recursive: bool,
+    /// see `Pattern::with_max_recursion_depth`
+    pub max_recursion_depth: Option<usize>,
+    /// see `Pattern::with_coerce_timestamps`
+    #[cfg(feature = "chrono")]
+    pub coerce_timestamps: bool,
+    /// see `Pattern::with_duplicates`
+    pub duplicates: DuplicatePolicy,
+    /// see `Pattern::with_value_array_separator`
+    pub value_array_separator: Option<String>,
+    /// see `Pattern::with_single_value_arrays`
+    pub single_value_arrays: bool,
+    /// see `Pattern::with_value_separator_mapping`, in order
+    pub value_separator_mappings: Vec<(String, String)>,
+    /// see `Pattern::with_normalize`
+    #[cfg(feature = "unicode-normalization")]
+    pub normalize: Option<NormForm>,
+    /// see `Pattern::with_split_strategy`
+    pub split_strategy: SplitStrategy,
+    /// a regular expression fields are split with instead of the field
+    /// separators, see `Pattern::compile_regex`
+    #[cfg(feature = "regex")]
+    pub field_regex: Option<String>,
+    /// a regular expression keys are split with instead of the key
+    /// separators, see `Pattern::compile_regex`
+    #[cfg(feature = "regex")]
+    pub key_regex: Option<String>,
+}
+
+fn strs(strings: &[String]) -> Vec<&str> {
+    strings.iter().map(String::as_str).collect()
+}
+
+/// Sets the limits on the size of inputs that are configured
+fn limits(mut b: PatternBuilder, config: &PatternConfig) -> PatternBuilder {
+    if let Some(max) = config.max_pairs {
+        b = b.max_pairs(max);
+    }
+    if let Some(max) = config.max_input_length {
+        b = b.max_input_length(max);
+    }
+    if let Some(max) = config.max_fields {
+        b = b.max_fields(max);
+    }
+    if let Some(max) = config.max_key_length {
+        b = b.max_key_length(max);
+    }
+    if let Some(max) = config.max_value_length {
+        b = b.max_value_length(max);
+    }
+    b
+}
+
+/// Sets how keys are nested, an explicit separator wins over
+/// `nested_keys`
+fn nesting(mut b: PatternBuilder, config: &PatternConfig) -> PatternBuilder {
+    if config.nested_keys {
+        b = b.nested_keys(true);
+    }
+    if let Some(sep) = &config.nesting_separator {
+        b = b.nesting_separator(sep);
+    }
+    b
+}
+
+/// Replaces the literal seperators with the configured regular expressions
+#[cfg(feature = "regex")]
+fn regexes(mut p: Pattern, config: &PatternConfig) -> Result<Pattern, Error> {
+    if let Some(re) = &config.field_regex {
+        p.field_seperators = Seperators::default();
+        p.field_regex = Some(RegexSeperator::compile(re, DEFAULT_REGEX_SIZE_LIMIT)?);
+    }
+    if let Some(re) = &config.key_regex {
+        p.key_seperators = Seperators::default();
+        p.key_regex = Some(RegexSeperator::compile(re, DEFAULT_REGEX_SIZE_LIMIT)?);
+    }
+    Ok(p)
+}
+
+impl Pattern {
+    /// Builds a pattern from its configuration
+    ///
+    /// # Errors
+    /// if a seperator is contained in another one or a regular expression
+    /// is invalid
+    pub fn from_config(config: &PatternConfig) -> Result<Self, Error> {
+        let mut b = Self::builder();
+        for sep in &config.field_separators {
+            b = b.field_separator(sep);
+        }
+        for sep in &config.key_separators {
+            b = b.key_separator(sep);
+        }
+        for quote in &config.quotes {
+            b = b.quote(*quote);
+        }
+        for (key, expected) in &config.expected_types {
+            b = b.expected_type(key, *expected);
+        }
+        for (from, to) in &config.value_separator_mappings {
+            b = b.value_separator_mapping(from, to);
+        }
+        if let Some(max) = config.max_distinct_keys {
+            b = b.max_distinct_keys(max);
+        }
+        if let Some(trim) = &config.trim_key {
+            b = b.trim_key(trim.clone());
+        }
+        if let Some(trim) = &config.trim_value {
+            b = b.trim_value(trim.clone());
+        }
+        if let Some(prefix) = &config.key_prefix {
+            b = b.key_prefix(prefix);
+        }
+        if let Some(key) = &config.leading_token_key {
+            b = b.leading_token_key(key);
+        }
+        if let Some(escape) = config.escape_char {
+            b = b.escape_char(escape);
+        }
+        if let Some(inner) = &config.then {
+            b = b.then(Self::from_config(inner)?);
+        }
+        b = nesting(b, config);
+        b = limits(b, config);
+        if let Some(sep) = &config.record_separator {
+            b = b.record_separator(sep);
+        }
+        if let Some(key) = &config.whole_as_value_key {
+            b = b.whole_as_value_key(key);
+        }
+        if let Some(depth) = config.max_total_depth {
+            b = b.max_total_depth(depth);
+        }
+        if let Some(depth) = config.max_recursion_depth {
+            b = b.max_recursion_depth(depth);
+        }
+        if let Some(sep) = &config.value_array_separator {
+            b = b.value_array_separator(sep);
+        }
+        #[cfg(feature = "unicode-normalization")]
+        if let Some(form) = config.normalize {
+            b = b.normalize(form);
+        }
+        #[cfg(feature = "chrono")]
+        {
+            b = b.coerce_timestamps(config.coerce_timestamps);
+        }
+        let pattern = b
+            .keep_quotes(config.keep_quotes)
+            .coerce_values(config.coerce_values)
+            .coercion_fallback(config.coercion_fallback)
+            .collapse_consecutive_separators(config.collapse_consecutive_separators)
+            .positional_keys(&strs(&config.positional_keys))
+            .include_keys(&strs(&config.include_keys))
+            .exclude_keys(&strs(&config.exclude_keys))
+            .respect_brackets(config.respect_brackets)
+            .strip_brackets(config.strip_brackets)
+            .whitespace_field_separator(config.whitespace_field_separator)
+            .strip_trailing_separator(config.strip_trailing_separator)
+            .split_once(config.split_once)
+            .strict_logfmt(config.strict_logfmt)
+            .strict(config.strict)
+            .percent_decode(config.percent_decode)
+            .invalid_utf8(config.invalid_utf8)
+            .flag_policy(config.flag_policy.clone())
+            .empty_values(config.empty_values)
+            .empty_keys(config.empty_keys.clone())
+            .strip_control_chars(config.strip_control_chars)
+            .strip_control_chars_in_keys(config.strip_control_chars_in_keys)
+            .nested_values(config.nested_values)
+            .trim_first_field(config.trim_first_field)
+            .array_index_notation(config.array_index_notation)
+            .index_gaps(config.index_gaps)
+            .dedupe_identical(config.dedupe_identical)
+            .recursive(config.recursive)
+            .duplicates(config.duplicates)
+            .single_value_arrays(config.single_value_arrays)
+            .split_strategy(config.split_strategy)
+            .build()?;
+        #[cfg(feature = "regex")]
+        let pattern = regexes(pattern, config)?;
+        Ok(pattern)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use simd_json::OwnedValue;
+
+    #[test]
+    fn from_config() {
+        assert_eq!(
+            Pattern::from_config(&PatternConfig::default()),
+            Ok(Pattern::default())
+        );
+
+        let mut json = br#"{
+            "field_separators": [";"],
+            "key_separators": ["="],
+            "quotes": ["\""],
+            "coerce_values": true,
+            "trim_key": "Whitespace",
+            "duplicates": "Array",
+            "then": {"key_separators": [":"]}
+        }"#
+        .to_vec();
+        let config: PatternConfig =
+            simd_json::serde::from_slice(&mut json).expect("Failed to parse config");
+        let kv = Pattern::from_config(&config).expect("Failed to build pattern");
+        assert_eq!(
+            kv,
+            Pattern::compile("%{key}=%{val};")
+                .expect("Failed to build pattern")
+                .with_quotes(&['"'])
+                .with_coerce_values(true)
+                .with_trim_key(Trim::Whitespace)
+                .with_duplicates(DuplicatePolicy::Array)
+                .then(Pattern::default())
+        );
+        let r: OwnedValue = kv
+            .run(r#" a =1; a=2;b="x;y";c=k:v"#)
+            .expect("Failed to split input");
+        assert_eq!(
+            r,
+            simd_json::json!({"a": [1, 2], "b": "x;y", "c": {"k": "v"}})
+        );
+
+        let mut json = br#"{"field_seperators": [";"]}"#.to_vec();
+        assert!(simd_json::serde::from_slice::<PatternConfig>(&mut json).is_err());
+
+        let config = PatternConfig {
+            nested_keys: true,
+            ..PatternConfig::default()
+        };
+        assert_eq!(
+            Pattern::from_config(&config),
+            Ok(Pattern::default().with_nested_keys(true))
+        );
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn from_config_regex() {
+        let mut json = br#"{"field_regex": "\\s*,\\s*", "key_regex": "\\s*=\\s*"}"#.to_vec();
+        let config: PatternConfig =
+            simd_json::serde::from_slice(&mut json).expect("Failed to parse config");
+        let kv = Pattern::from_config(&config).expect("Failed to build pattern");
+        assert_eq!(
+            kv,
+            Pattern::compile_regex(r"\s*,\s*", r"\s*=\s*").expect("Failed to build pattern")
+        );
+        let r: OwnedValue = kv.run("a = 1 , b=2").expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"a": "1", "b": "2"}));
+
+        let config = PatternConfig {
+            key_regex: Some("(".to_string()),
+            ..PatternConfig::default()
+        };
+        assert!(matches!(
+            Pattern::from_config(&config),
+            Err(Error::InvalidRegex(p, _)) if p == "("
+        ));
+    }
+}
@@ -0,0 +1,59 @@
+// Copyright 2020-2021, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Parsing HTTP `Cookie` headers, e.g. `SID=31d4d96e; lang=en-US`
+
+use crate::{Pattern, Trim};
+
+impl Pattern {
+    /// A pattern for the value of HTTP `Cookie` headers (RFC 6265): cookies
+    /// are seperated by `; `, names and values by the first `=` so values
+    /// can contain `=`, and double quoted values are unquoted.
+    #[must_use]
+    pub fn cookies() -> Self {
+        Self {
+            field_seperators: vec![";".to_string()].into(),
+            key_seperators: vec!["=".to_string()].into(),
+            ..Self::default()
+        }
+        .with_quotes(&['"'])
+        .with_split_once(true)
+        .with_trim_key(Trim::Whitespace)
+        .with_trim_value(Trim::Whitespace)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use simd_json::OwnedValue;
+
+    #[test]
+    fn cookies() {
+        let kv = Pattern::cookies();
+        let r: OwnedValue = kv
+            .run(r#"SID=31d4d96e407aad42; lang=en-US;token=YWJj==; theme="dark mode"; empty="#)
+            .expect("Failed to split input");
+        assert_eq!(
+            r,
+            simd_json::json!({
+                "SID": "31d4d96e407aad42",
+                "lang": "en-US",
+                "token": "YWJj==",
+                "theme": "dark mode",
+                "empty": ""
+            })
+        );
+    }
+}
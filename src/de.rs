@@ -0,0 +1,516 @@
+// Copyright 2020-2021, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Deserializing KV text directly into user types
+
+use crate::value::{InvalidValue, Val};
+use crate::{Depth, Error, Pattern};
+use serde::de::value::CowStrDeserializer;
+use serde::de::{self, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Visitor};
+use serde::{forward_to_deserialize_any, Deserialize};
+use std::borrow::Cow;
+use std::fmt;
+use std::vec;
+
+/// An error deserializing KV text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeserializeError(String);
+
+impl fmt::Display for DeserializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for DeserializeError {}
+
+impl de::Error for DeserializeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+/// Deserializes the pairs `pattern` splits from `input` into a `T`, e.g. a
+/// struct with a field per key.
+///
+/// The pairs are collected like with `run`, so all options apply, and the
+/// resulting object is deserialized. Values that are still strings are
+/// parsed into the type of the field they are deserialized into, so
+/// `port=8080` deserializes into a `port: u16` even without
+/// `with_coerce_values`, and a single value deserializes into a sequence
+/// of one. Where a single value is expected but there is an array, e.g.
+/// with `DuplicatePolicy::Array`, the last element is used.
+///
+/// # Errors
+/// if the pairs don't fit `T`, e.g. a required field is missing or a value
+/// does not parse as the type of its field
+pub fn from_str<'input, T>(pattern: &Pattern, input: &'input str) -> Result<T, DeserializeError>
+where
+    T: Deserialize<'input>,
+{
+    pattern.deserialize_into(input)
+}
+
+impl Pattern {
+    /// Deserializes the pairs split from `input` into a `T`, see
+    /// `from_str`.
+    ///
+    /// # Errors
+    /// if the pairs don't fit `T`
+    pub fn deserialize_into<'input, T>(&self, input: &'input str) -> Result<T, DeserializeError>
+    where
+        T: Deserialize<'input>,
+    {
+        let obj = match self.obj(self.extract(input), Depth::top(self)) {
+            Ok(obj) => obj.unwrap_or_default(),
+            Err(InvalidValue::Depth) => {
+                return Err(de::Error::custom(Error::MaxDepthExceeded(
+                    self.depth_limit(),
+                )))
+            }
+            Err(InvalidValue::Type) => {
+                return Err(de::Error::custom("a value does not have its expected type"))
+            }
+        };
+        T::deserialize(Value(Val::Object(obj)))
+    }
+}
+
+/// A value as collected from the pairs
+struct Value<'input>(Val<'input>);
+
+impl<'input> Value<'input> {
+    /// The single value, the last one of an array
+    fn scalar(self) -> Scalar<'input> {
+        match self.0 {
+            Val::Array(mut a) => Scalar(a.pop().unwrap_or(Val::Null)),
+            val => Scalar(val),
+        }
+    }
+}
+
+macro_rules! forward_to_scalar {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                self.scalar().$method(visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Value<'de> {
+    type Error = DeserializeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            Val::Str(s) => visit_str(s, visitor),
+            Val::I64(i) => visitor.visit_i64(i),
+            Val::F64(f) => visitor.visit_f64(f),
+            Val::Bool(b) => visitor.visit_bool(b),
+            Val::Null | Val::Missing => visitor.visit_unit(),
+            Val::Array(a) => visitor.visit_seq(Seq(a.into_iter())),
+            Val::Object(o) => visitor.visit_map(Map {
+                entries: o.into_entries(),
+                val: None,
+            }),
+        }
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let vals = match self.0 {
+            Val::Array(a) => a,
+            val => vec![val],
+        };
+        visitor.visit_seq(Seq(vals.into_iter()))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            Val::Null | Val::Missing => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.scalar().deserialize_unit_struct(name, visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.scalar().deserialize_struct(name, fields, visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.scalar().deserialize_enum(name, variants, visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    forward_to_scalar! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_i128 deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+        deserialize_u128 deserialize_f32 deserialize_f64 deserialize_char deserialize_str
+        deserialize_string deserialize_bytes deserialize_byte_buf deserialize_unit
+        deserialize_map deserialize_identifier
+    }
+}
+
+/// The entries of an object as a map
+struct Map<'input, I> {
+    entries: I,
+    val: Option<Val<'input>>,
+}
+
+impl<'de, I> MapAccess<'de> for Map<'de, I>
+where
+    I: ExactSizeIterator<Item = (Cow<'de, str>, Val<'de>)>,
+{
+    type Error = DeserializeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        let Some((key, val)) = self.entries.next() else {
+            return Ok(None);
+        };
+        self.val = Some(val);
+        let key: CowStrDeserializer<'de, DeserializeError> = key.into_deserializer();
+        seed.deserialize(key).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        let val = self
+            .val
+            .take()
+            .ok_or_else(|| de::Error::custom("value requested before its key"))?;
+        seed.deserialize(Value(val))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+/// The elements of an array as a sequence
+struct Seq<'input>(vec::IntoIter<Val<'input>>);
+
+impl<'de> SeqAccess<'de> for Seq<'de> {
+    type Error = DeserializeError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Self::Error> {
+        self.0
+            .next()
+            .map(|val| seed.deserialize(Value(val)))
+            .transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.0.len())
+    }
+}
+
+fn visit_str<'de, V: Visitor<'de>>(
+    s: Cow<'de, str>,
+    visitor: V,
+) -> Result<V::Value, DeserializeError> {
+    match s {
+        Cow::Borrowed(s) => visitor.visit_borrowed_str(s),
+        Cow::Owned(s) => visitor.visit_string(s),
+    }
+}
+
+/// A single value, strings are parsed into the type that is asked for
+struct Scalar<'input>(Val<'input>);
+
+impl Scalar<'_> {
+    fn parse<T: std::str::FromStr>(s: &str, expected: &str) -> Result<T, DeserializeError> {
+        s.parse()
+            .map_err(|_| de::Error::custom(format!("invalid {expected} '{s}'")))
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident $expected:literal,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                match &self.0 {
+                    Val::Str(s) => visitor.$visit(Self::parse(s, $expected)?),
+                    _ => self.deserialize_any(visitor),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Scalar<'de> {
+    type Error = DeserializeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        Value(self.0).deserialize_any(visitor)
+    }
+
+    deserialize_parsed! {
+        deserialize_bool => visit_bool "boolean",
+        deserialize_i8 => visit_i8 "integer",
+        deserialize_i16 => visit_i16 "integer",
+        deserialize_i32 => visit_i32 "integer",
+        deserialize_i64 => visit_i64 "integer",
+        deserialize_i128 => visit_i128 "integer",
+        deserialize_u8 => visit_u8 "integer",
+        deserialize_u16 => visit_u16 "integer",
+        deserialize_u32 => visit_u32 "integer",
+        deserialize_u64 => visit_u64 "integer",
+        deserialize_u128 => visit_u128 "integer",
+        deserialize_f32 => visit_f32 "float",
+        deserialize_f64 => visit_f64 "float",
+        deserialize_char => visit_char "character",
+    }
+
+    /// Coerced values deserialize into strings as they were written
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            Val::Str(s) => visit_str(s, visitor),
+            Val::I64(i) => visitor.visit_string(i.to_string()),
+            Val::F64(f) => visitor.visit_string(f.to_string()),
+            Val::Bool(b) => visitor.visit_string(b.to_string()),
+            Val::Null => visitor.visit_borrowed_str("null"),
+            val => Value(val).deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            Val::Str(s) => {
+                let val: CowStrDeserializer<'de, DeserializeError> = s.into_deserializer();
+                visitor.visit_enum(val)
+            }
+            val => Value(val).deserialize_any(visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bytes byte_buf option seq tuple tuple_struct map struct newtype_struct
+        ignored_any
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{DuplicatePolicy, FlagPolicy};
+    use std::collections::HashMap;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    enum Level {
+        Info,
+        Error,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Config<'a> {
+        host: &'a str,
+        port: u16,
+        tls: bool,
+        ratio: Option<f64>,
+        level: Level,
+        #[serde(default)]
+        tags: Vec<String>,
+    }
+
+    #[test]
+    fn from_str() {
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        let c: Config = super::from_str(&kv, "host=a port=8080 tls=true level=info extra=1")
+            .expect("Failed to deserialize");
+        assert_eq!(
+            c,
+            Config {
+                host: "a",
+                port: 8080,
+                tls: true,
+                ratio: None,
+                level: Level::Info,
+                tags: Vec::new(),
+            }
+        );
+
+        let kv = kv
+            .with_duplicates(DuplicatePolicy::Array)
+            .with_value_array_separator(",");
+        let c: Config = kv
+            .deserialize_into("host=a port=1 tls=false ratio=0.5 level=error tags=x,y")
+            .expect("Failed to deserialize");
+        assert_eq!(c.ratio, Some(0.5));
+        assert_eq!(c.level, Level::Error);
+        assert_eq!(c.tags, ["x", "y"]);
+        let c: Config = kv
+            .deserialize_into("host=a port=1 tls=false level=info tags=x tags=z")
+            .expect("Failed to deserialize");
+        assert_eq!(c.tags, ["x", "z"]);
+
+        let e = super::from_str::<Config>(&kv, "host=a port=http tls=true level=info")
+            .expect_err("port is no integer");
+        assert_eq!(e.to_string(), "invalid integer 'http'");
+        assert!(super::from_str::<Config>(&kv, "host=a").is_err());
+
+        let m: HashMap<String, u8> =
+            super::from_str(&kv, "a=1 b=2").expect("Failed to deserialize");
+        assert_eq!(m.get("b"), Some(&2));
+
+        let kv = kv
+            .with_duplicates(DuplicatePolicy::Max)
+            .with_coerce_values(true);
+        let m: HashMap<String, String> = kv
+            .deserialize_into("n=5 n=30 n=9 f=1.5 f=-2 s=a s=b")
+            .expect("Failed to deserialize");
+        assert_eq!(m["n"], "30");
+        assert_eq!(m["f"], "1.5");
+        assert_eq!(m["s"], "b");
+        let kv = kv.with_duplicates(DuplicatePolicy::Min);
+        let m: HashMap<String, i8> = kv
+            .deserialize_into("n=5 n=30 n=9 f=1 f=-2")
+            .expect("Failed to deserialize");
+        assert_eq!(m["n"], 5);
+        assert_eq!(m["f"], -2);
+        let kv = kv.with_duplicates(DuplicatePolicy::Overwrite);
+        let m: HashMap<String, u8> = kv
+            .deserialize_into("n=5 n=30 n=9")
+            .expect("Failed to deserialize");
+        assert_eq!(m["n"], 9);
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Inner {
+        b: u8,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Outer {
+        a: Inner,
+    }
+
+    #[test]
+    fn options_apply() {
+        let kv = Pattern::default().with_flag_policy(FlagPolicy::Collect("flags".to_string()));
+        let m: HashMap<String, Vec<String>> = kv
+            .deserialize_into("debug verbose a:1")
+            .expect("Failed to deserialize");
+        assert_eq!(m["flags"], ["debug", "verbose"]);
+
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_nested_keys(true);
+        let o: Outer = kv.deserialize_into("a.b=1").expect("Failed to deserialize");
+        assert_eq!(o, Outer { a: Inner { b: 1 } });
+
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .then(Pattern::default());
+        let m: HashMap<String, HashMap<String, String>> =
+            kv.deserialize_into("c=k:v").expect("Failed to deserialize");
+        assert_eq!(m["c"]["k"], "v");
+
+        let kv = Pattern::compile("%{key}=%{val},%{key}:%{val}")
+            .expect("Failed to build pattern")
+            .with_quotes(&['"'])
+            .with_recursive(true);
+        let m: HashMap<String, HashMap<String, String>> = kv
+            .deserialize_into(r#"outer="inner1:a,inner2:b""#)
+            .expect("Failed to deserialize");
+        assert_eq!(m["outer"]["inner2"], "b");
+
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_dedupe_identical(true)
+            .with_duplicates(DuplicatePolicy::Array);
+        let m: HashMap<String, Vec<u8>> = kv
+            .deserialize_into("x=1 x=1 x=2")
+            .expect("Failed to deserialize");
+        assert_eq!(m["x"], [1, 2]);
+    }
+}
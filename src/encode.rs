@@ -0,0 +1,215 @@
+// Copyright 2020-2021, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Encoding objects back into KV strings
+
+use crate::{Error, Pattern};
+use std::borrow::Cow;
+use value_trait::prelude::*;
+
+impl Pattern {
+    /// Encodes an object into a KV string using the first field and key
+    /// seperator of the pattern, e.g. `a=1 b=2` for `%{key}=%{val}`.
+    ///
+    /// Keys and values containing a seperator or quote are escaped with the
+    /// escape character if there is one, otherwise they are wrapped in a
+    /// quote they do not contain. Arrays are joined with the value array
+    /// seperator, numbers, booleans and `null` are written as in JSON.
+    ///
+    /// # Errors
+    /// `Error::NotAnObject` if the value is no object, `Error::Unencodable`
+    /// for keys and values that can not be encoded so they parse back into
+    /// the same text, e.g. nested objects or values containing seperators
+    /// without quotes or an escape character to protect them, as well as
+    /// pairs that need a seperator the pattern only has as a regular
+    /// expression
+    pub fn encode<V>(&self, value: &V) -> Result<String, Error>
+    where
+        V: ValueAsContainer<
+                Object: ObjectTrait<Key: AsRef<str>, Element = V>,
+                Array: ArrayTrait<Element = V>,
+            > + ValueAsScalar
+            + Writable,
+    {
+        let obj = value.as_object().ok_or(Error::NotAnObject)?;
+        let mut r = String::new();
+        for (key, val) in obj.iter() {
+            if let Some(a) = val.as_array() {
+                let sep = self
+                    .value_array_separator
+                    .as_deref()
+                    .ok_or_else(|| Error::Unencodable(val.encode()))?;
+                let mut vals = Vec::with_capacity(a.len());
+                for v in a.iter() {
+                    let v = scalar_text(v)?;
+                    if v.contains(sep) {
+                        return Err(Error::Unencodable(v.into_owned()));
+                    }
+                    vals.push(v);
+                }
+                self.push_pair(&mut r, key.as_ref(), &vals.join(sep))?;
+            } else {
+                self.push_pair(&mut r, key.as_ref(), &scalar_text(val)?)?;
+            }
+        }
+        Ok(r)
+    }
+
+    /// Appends a pair to an encoded string, seperated from the pairs before
+    pub(crate) fn push_pair(&self, out: &mut String, key: &str, val: &str) -> Result<(), Error> {
+        let unencodable = || Error::Unencodable(key.to_string());
+        if !out.is_empty() {
+            out.push_str(self.field_seperators.first().ok_or_else(unencodable)?);
+        }
+        out.push_str(&self.escape(key)?);
+        out.push_str(self.key_seperators.first().ok_or_else(unencodable)?);
+        out.push_str(&self.escape(val)?);
+        Ok(())
+    }
+
+    /// Protects the seperators and quotes in a key or value so they are
+    /// taken literally when parsed
+    fn escape<'s>(&self, s: &'s str) -> Result<Cow<'s, str>, Error> {
+        let special = |rest: &str| {
+            self.field_seperators
+                .iter()
+                .chain(self.key_seperators.iter())
+                .any(|sep| !sep.is_empty() && rest.starts_with(sep.as_str()))
+                || self.regex_seperator_at(rest)
+                || rest.starts_with(&self.quotes[..])
+                || self.escape_char.is_some_and(|e| rest.starts_with(e))
+        };
+        if !s.char_indices().any(|(i, _)| special(&s[i..])) {
+            return Ok(Cow::Borrowed(s));
+        }
+        if let Some(escape) = self.escape_char {
+            let mut r = String::with_capacity(s.len() + 1);
+            for (i, c) in s.char_indices() {
+                if special(&s[i..]) {
+                    r.push(escape);
+                }
+                r.push(c);
+            }
+            return Ok(Cow::Owned(r));
+        }
+        match self.quotes.iter().find(|q| !s.contains(**q)) {
+            Some(q) if !s.ends_with('\\') => Ok(Cow::Owned(format!("{q}{s}{q}"))),
+            _ => Err(Error::Unencodable(s.to_string())),
+        }
+    }
+}
+
+impl Pattern {
+    /// If a regular expression seperator matches at the start of `rest`
+    #[cfg(feature = "regex")]
+    fn regex_seperator_at(&self, rest: &str) -> bool {
+        self.field_regex
+            .iter()
+            .chain(self.key_regex.iter())
+            .filter_map(|re| re.0.find(rest))
+            .any(|m| m.start() == 0 && !m.is_empty())
+    }
+
+    #[cfg(not(feature = "regex"))]
+    #[allow(clippy::unused_self)]
+    fn regex_seperator_at(&self, _rest: &str) -> bool {
+        false
+    }
+}
+
+/// The text of a scalar value
+fn scalar_text<V>(val: &V) -> Result<Cow<'_, str>, Error>
+where
+    V: ValueAsContainer + ValueAsScalar + Writable,
+{
+    if let Some(s) = val.as_str() {
+        Ok(Cow::Borrowed(s))
+    } else if val.as_array().is_some() || val.as_object().is_some() {
+        Err(Error::Unencodable(val.encode()))
+    } else {
+        Ok(Cow::Owned(val.encode()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use simd_json::{BorrowedValue, OwnedValue};
+
+    #[test]
+    fn encode() {
+        let kv = Pattern::compile("%{key}=%{val}&")
+            .expect("Failed to build pattern")
+            .with_coerce_values(true);
+        let v = simd_json::json!({"a": 1});
+        assert_eq!(kv.encode(&v), Ok("a=1".to_string()));
+
+        let v = simd_json::json!({"a": 1, "b": "x y", "c": true, "d": null, "e": 2.5});
+        let s = kv.encode(&v).expect("Failed to encode");
+        let r: OwnedValue = kv.run(&s).expect("Failed to split input");
+        assert_eq!(r, v);
+
+        // seperators in values need quotes or an escape character
+        let v = simd_json::json!({"q": "a=b&c"});
+        assert_eq!(kv.encode(&v), Err(Error::Unencodable("a=b&c".to_string())));
+        let quoted = kv.clone().with_quotes(&['"', '\'']);
+        assert_eq!(quoted.encode(&v), Ok("q=\"a=b&c\"".to_string()));
+        let v = simd_json::json!({"q": "say \"a=b\""});
+        assert_eq!(quoted.encode(&v), Ok("q='say \"a=b\"'".to_string()));
+        let escaped = kv.clone().with_escape_char('\\');
+        let v = simd_json::json!({"k&": "a=b\\"});
+        let s = escaped.encode(&v).expect("Failed to encode");
+        assert_eq!(s, r"k\&=a\=b\\");
+        let r: OwnedValue = escaped.run(&s).expect("Failed to split input");
+        assert_eq!(r, v);
+
+        let v = simd_json::json!({"tags": ["a", "b", 1]});
+        assert_eq!(
+            kv.encode(&v),
+            Err(Error::Unencodable("[\"a\",\"b\",1]".to_string()))
+        );
+        let arrays = kv.clone().with_value_array_separator(",");
+        assert_eq!(arrays.encode(&v), Ok("tags=a,b,1".to_string()));
+
+        assert_eq!(
+            kv.encode(&simd_json::json!({"n": {"x": 1}})),
+            Err(Error::Unencodable("{\"x\":1}".to_string()))
+        );
+        assert_eq!(
+            kv.encode(&BorrowedValue::from("x")),
+            Err(Error::NotAnObject)
+        );
+        assert_eq!(kv.encode(&simd_json::json!({})), Ok(String::new()));
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn encode_regex() {
+        let kv = Pattern::compile(r"%{key}=%{val}/re:\s*;\s*/")
+            .expect("Failed to build pattern")
+            .with_quotes(&['"']);
+        let v = simd_json::json!({"a": "1", "b": "2"});
+        assert_eq!(kv.encode(&v), Err(Error::Unencodable("b".to_string())));
+
+        let v = simd_json::json!({"a": "x ; y"});
+        let s = kv.encode(&v).expect("Failed to encode");
+        assert_eq!(s, "a=\"x ; y\"");
+        let r: OwnedValue = kv.run(&s).expect("Failed to split input");
+        assert_eq!(r, v);
+
+        let kv = Pattern::compile(r"%{key}/re:\s*=\s*/%{val} ").expect("Failed to build pattern");
+        let v = simd_json::json!({"a": "1"});
+        assert_eq!(kv.encode(&v), Err(Error::Unencodable("a".to_string())));
+    }
+}
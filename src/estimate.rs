@@ -0,0 +1,51 @@
+// Copyright 2020-2021, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Estimating the memory used by parsing
+
+use crate::{Pair, Pattern, RawPair, Val};
+use simd_json::OwnedValue;
+use std::borrow::Cow;
+use std::mem::size_of;
+
+impl Pattern {
+    /// Estimates the heap bytes `run` uses to produce an owned output for
+    /// `input`, that is the keys, the string values and the object they
+    /// are stored in as well as the temporary allocations made while
+    /// parsing. This is an approximation that ignores allocator overhead.
+    pub fn estimate_bytes(&self, input: &str) -> usize {
+        let pairs = self.extract(input);
+        if pairs.is_empty() {
+            return 0;
+        }
+        // keys and values are copied into the output
+        let strings: usize = pairs.iter().map(|p| p.key.len() + p.val.len()).sum();
+        // per pair the slot in the output and unless the pairs are inserted
+        // right away the fields and key value splits, the raw and extracted
+        // pairs and the intermediate object with its index, growing
+        // collections allocate about twice that
+        let slot = size_of::<(String, OwnedValue)>();
+        let per_pair = if self.direct_seperators().is_some() {
+            slot
+        } else {
+            3 * size_of::<&str>()
+                + size_of::<RawPair>()
+                + size_of::<Pair>()
+                + size_of::<Val>()
+                + 2 * size_of::<(Cow<str>, usize)>()
+                + slot
+        };
+        strings + pairs.len() * per_pair * 2
+    }
+}
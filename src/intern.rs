@@ -0,0 +1,139 @@
+// Copyright 2020-2021, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Interning of keys and values for owned outputs
+
+use crate::{Pair, Pattern};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// Interns the strings of parsed outputs so repeated keys, and optionally
+/// values, share one allocation across many parses.
+#[derive(Debug, Default, Clone)]
+pub struct Interner {
+    strings: HashSet<Arc<str>>,
+    values: bool,
+}
+
+impl Interner {
+    /// Creates an interner that interns keys
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets if values are interned as well as keys, this pays off when
+    /// values repeat heavily (e.g. `status=200`).
+    #[must_use]
+    pub fn with_values(mut self, values: bool) -> Self {
+        self.values = values;
+        self
+    }
+
+    /// Returns the shared copy of `s`, adding it if it wasn't interned yet
+    pub fn intern(&mut self, s: &str) -> Arc<str> {
+        if let Some(interned) = self.strings.get(s) {
+            interned.clone()
+        } else {
+            let interned: Arc<str> = Arc::from(s);
+            self.strings.insert(interned.clone());
+            interned
+        }
+    }
+
+    /// The number of interned strings
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    /// Checks if no strings are interned
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    /// Drops all interned strings, outputs that still hold them keep them
+    /// alive
+    pub fn clear(&mut self) {
+        self.strings.clear();
+    }
+}
+
+impl Pattern {
+    /// Splits a string that represents KV pairs into an owned map whose
+    /// keys (and values if the interner is configured for it) are shared
+    /// through the interner.
+    ///
+    /// Values are not coerced, the map always holds the raw strings and
+    /// later values of a repeated key overwrite earlier ones.
+    pub fn run_interned(
+        &self,
+        input: &str,
+        interner: &mut Interner,
+    ) -> Option<HashMap<Arc<str>, Arc<str>>> {
+        let pairs = self.extract(input);
+        if pairs.is_empty() {
+            return None;
+        }
+        let mut r = HashMap::with_capacity(pairs.len());
+        for Pair { key, val, .. } in pairs {
+            let val = if interner.values {
+                interner.intern(&val)
+            } else {
+                Arc::from(&*val)
+            };
+            r.insert(interner.intern(&key), val);
+        }
+        Some(r)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn interned_keys() {
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        let mut interner = Interner::new();
+        let r1 = kv
+            .run_interned("status=200 path=/", &mut interner)
+            .expect("Failed to split input");
+        let r2 = kv
+            .run_interned("status=200 path=/a", &mut interner)
+            .expect("Failed to split input");
+        assert_eq!(interner.len(), 2);
+        let (k1, v1) = r1.get_key_value("status").expect("no status");
+        let (k2, v2) = r2.get_key_value("status").expect("no status");
+        assert!(Arc::ptr_eq(k1, k2));
+        assert_eq!(v1, v2);
+        assert!(!Arc::ptr_eq(v1, v2));
+    }
+
+    #[test]
+    fn interned_values() {
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        let mut interner = Interner::new().with_values(true);
+        let r1 = kv
+            .run_interned("status=200 path=/", &mut interner)
+            .expect("Failed to split input");
+        let r2 = kv
+            .run_interned("status=200 path=/a", &mut interner)
+            .expect("Failed to split input");
+        assert_eq!(interner.len(), 5);
+        let v1 = r1.get("status").expect("no status");
+        let v2 = r2.get("status").expect("no status");
+        assert_eq!(&**v1, "200");
+        assert!(Arc::ptr_eq(v1, v2));
+        assert!(kv.run_interned("nothing", &mut interner).is_none());
+    }
+}
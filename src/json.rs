@@ -0,0 +1,117 @@
+// Copyright 2020-2021, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Producing `serde_json` values and grafting them into documents
+
+use crate::{Depth, Pattern};
+use serde_json::{Map, Value};
+
+impl Pattern {
+    /// Splits a string that represents KV pairs like `run` into a
+    /// `serde_json` object. Floats JSON can't represent become `null`.
+    pub fn run_json(&self, input: &str) -> Option<Value> {
+        self.run_json_map(input).map(Value::Object)
+    }
+
+    /// Splits a string that represents KV pairs like `run_json` but returns
+    /// the map of the object.
+    pub fn run_json_map(&self, input: &str) -> Option<Map<String, Value>> {
+        Some(
+            self.obj(self.extract(input), Depth::top(self))
+                .ok()??
+                .into_json(),
+        )
+    }
+
+    /// Splits a string that represents KV pairs like `run` and inserts the
+    /// resulting object into `target` at `path`, missing intermediate
+    /// objects are created and an empty path replaces `target`.
+    ///
+    /// Returns `false` and leaves `target` untouched if the input does not
+    /// produce an output or a value on the path is not an object.
+    pub fn run_into_json_at(&self, input: &str, target: &mut Value, path: &[&str]) -> bool {
+        let Some(r) = self.run_json(input) else {
+            return false;
+        };
+        let Some((last, parents)) = path.split_last() else {
+            *target = r;
+            return true;
+        };
+        let mut obj = target;
+        for key in parents {
+            match obj {
+                Value::Object(m) => {
+                    obj = m.entry(*key).or_insert_with(|| Value::Object(Map::new()));
+                }
+                _ => return false,
+            }
+        }
+        if let Value::Object(m) = obj {
+            m.insert((*last).to_string(), r);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn run_json() {
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_coerce_values(true)
+            .with_value_array_separator(",")
+            .with_nested_keys(true);
+        assert_eq!(
+            kv.run_json("a=1 b=two c=1.5,true d=null e.f=x"),
+            Some(json!({
+                "a": 1,
+                "b": "two",
+                "c": [1.5, true],
+                "d": null,
+                "e": {"f": "x"}
+            }))
+        );
+        let m = kv.run_json_map("a=1").expect("Failed to split input");
+        assert_eq!(m["a"], json!(1));
+        assert_eq!(kv.run_json("nothing"), None);
+    }
+
+    #[test]
+    fn run_into_json_at() {
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_coerce_values(true);
+        let mut target = json!({"id": 1, "meta": {"source": "x"}});
+        assert!(kv.run_into_json_at("a=1 b=two", &mut target, &["meta", "kv"]));
+        assert_eq!(
+            target,
+            json!({"id": 1, "meta": {"source": "x", "kv": {"a": 1, "b": "two"}}})
+        );
+        assert!(kv.run_into_json_at("c=3", &mut target, &["new", "deep", "kv"]));
+        assert_eq!(target["new"]["deep"]["kv"], json!({"c": 3}));
+
+        assert!(!kv.run_into_json_at("a=1", &mut target, &["id", "kv"]));
+        assert!(!kv.run_into_json_at("nothing", &mut target, &["meta", "kv"]));
+        assert_eq!(target["meta"]["kv"], json!({"a": 1, "b": "two"}));
+
+        assert!(kv.run_into_json_at("a=1", &mut target, &[]));
+        assert_eq!(target, json!({"a": 1}));
+    }
+}
@@ -24,28 +24,28 @@
 //
 // | Setting                | Translation                                             | Supported |
 // |------------------------|---------------------------------------------------------|-----------|
-// | allow_duplicate_values | not supported, since we deal with JSON maps             | No        |
+// | allow_duplicate_values | supported, via `DuplicatePolicy::Array`                 | Yes       |
 // | default_keys           | should be handled in TS (via assignment)                | TS        |
-// | exclude_keys           | should behandled in TS (via delete_keys?)               | TS        |
+// | exclude_keys           | supported, via `with_exclude_keys`                      | Yes       |
 // | field_split            | supported, array of strings                             | Yes       |
-// | field_split_pattern    | not supported                                           | No        |
-// | include_brackets       | should be handled in TS (via map + dissect?)            | TS        |
-// | include_keys           | should be handled in TS (via select)                    | TS        |
-// | prefix                 | should be handled in TS (via map + string::format)      | TS        |
-// | recursive              | not supported                                           | No        |
+// | field_split_pattern    | supported, via `/re:.../` seperators (`regex` feature)  | Yes       |
+// | include_brackets       | supported, via `with_respect_brackets`                  | Yes       |
+// | include_keys           | supported, via `with_include_keys`                      | Yes       |
+// | prefix                 | supported, via `with_key_prefix`                        | Yes       |
+// | recursive              | supported, via `with_recursive`                         | Yes       |
 // | remove_char_key        | should be handled in TS (via map + re::replace)         | TS        |
 // | remove_char_value      | should be handled in TS (via map + re::replace)         | TS        |
 // | source                 | handled in TS at call time                              | TS        |
 // | target                 | handled in TS at return time                            | TS        |
 // | tag_on_failure         | handled in TS at return time                            | TS        |
-// | tag_on_timeout         | currently not supported                                 | No        |
-// | timeout_millis         | currently not supported                                 | No        |
+// | tag_on_timeout         | handled in TS on `Error::Timeout`                       | TS        |
+// | timeout_millis         | supported, via `run_with_deadline`                      | Yes       |
 // | transform_key          | should be handled in TS (via map + ?)                   | TS        |
 // | transform_value        | should be handled in TS (via map + ?)                   | TS        |
-// | trim_key               | should be handled in TS (via map + ?)                   | TS        |
-// | trim_value             | should be handled in TS (via map + ?)                   | TS        |
+// | trim_key               | supported, via `with_trim_key`                          | Yes       |
+// | trim_value             | supported, via `with_trim_value`                        | Yes       |
 // | value_split            | supported, array of strings                             | Yes       |
-// | value_split_pattern    | not supported                                           | No        |
+// | value_split_pattern    | supported, via `/re:.../` seperators (`regex` feature)  | Yes       |
 // | whitespace             | we always run in 'lenient mode' as is the default of LS | No        |
 #![deny(warnings)]
 #![recursion_limit = "1024"]
@@ -57,9 +57,74 @@
 )]
 #![allow(clippy::must_use_candidate)]
 
-use serde::{Deserialize, Serialize};
-use simd_json::prelude::{MutableObject, *};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+#[cfg(feature = "simd-json")]
+use simd_json::OwnedValue;
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fmt;
+use std::hash::{Hash, Hasher};
+#[cfg(feature = "simd-json")]
+use std::io::{self, BufRead};
+use std::time::Instant;
+use value_trait::prelude::*;
+
+#[cfg(feature = "bumpalo")]
+mod arena;
+mod batch;
+mod builder;
+mod bytes;
+#[cfg(feature = "channel")]
+mod channel;
+mod ci;
+mod config;
+mod cookie;
+mod de;
+mod encode;
+#[cfg(feature = "simd-json")]
+mod estimate;
+mod intern;
+#[cfg(feature = "serde_json")]
+mod json;
+mod logfmt;
+#[cfg(feature = "unicode-normalization")]
+mod normalize;
+#[cfg(feature = "indexmap")]
+mod ordered;
+pub mod pattern_string;
+mod properties;
+mod query;
+#[cfg(feature = "regex")]
+mod re;
+mod report;
+pub mod sd;
+mod segment;
+mod ser;
+pub mod stream;
+mod suggest;
+#[cfg(feature = "chrono")]
+mod timestamp;
+mod value;
+mod view;
+
+pub use batch::BatchResult;
+pub use builder::PatternBuilder;
+pub use ci::CiMap;
+pub use config::PatternConfig;
+pub use de::{from_str, DeserializeError};
+pub use intern::Interner;
+#[cfg(feature = "unicode-normalization")]
+pub use normalize::NormForm;
+#[cfg(feature = "regex")]
+use re::RegexSeperator;
+#[cfg(feature = "regex")]
+pub use re::DEFAULT_REGEX_SIZE_LIMIT;
+pub use report::{DropReason, FieldError, ParseError, Report};
+pub use segment::Segment;
+pub use ser::{to_string, SerializeError};
+use value::{InvalidValue, Obj, Val};
+pub use view::PatternView;
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum Error {
@@ -67,6 +132,21 @@ pub enum Error {
     DoubleSeperator(String),
     InvalidEscape(char),
     UnterminatedEscape,
+    InvalidHeader(String),
+    UnexpectedVal(usize),
+    MissingKeySeperator(usize),
+    InvalidRegex(String, String),
+    RegexTooComplex(String, usize),
+    NoFieldSeperator(String),
+    NoKeySeperator(String),
+    MaxDepthExceeded(usize),
+    NotAnObject,
+    Unencodable(String),
+    InvalidUtf8(usize),
+    LimitExceeded(Limit),
+    Timeout,
+    EmptyKey(usize),
+    UnsplitField(String, usize),
 }
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -75,24 +155,450 @@ impl fmt::Display for Error {
             Self::DoubleSeperator(s) => write!(f, "The seperator '{s}' is used for both key value seperation as well as pair seperation."),
             Self::InvalidEscape(s) => write!(f, "Invalid escape sequence \\'{s}' is not valid."),
             Self::UnterminatedEscape => write!(f, "Unterminated escape at the end of line or of a delimiter %{{ can't be escaped"),
+            Self::InvalidHeader(s) => write!(f, "Invalid pattern header at '{s}'."),
+            Self::UnexpectedVal(p) => write!(f, "%{{val}} at character {p} is not preceded by a %{{key}}"),
+            Self::MissingKeySeperator(p) => write!(f, "missing key seperator between %{{key}} and %{{val}} at character {p}"),
+            Self::InvalidRegex(r, e) => write!(f, "Invalid regular expression '{r}': {e}"),
+            Self::RegexTooComplex(r, l) => write!(f, "The regular expression '{r}' exceeds the size limit of {l} bytes"),
+            Self::NoFieldSeperator(p) => write!(f, "The pattern '{p}' declares no field seperator"),
+            Self::NoKeySeperator(p) => write!(f, "The pattern '{p}' declares no key seperator, use %{{key}}<seperator>%{{val}}"),
+            Self::NotAnObject => write!(f, "Only objects can be encoded"),
+            Self::InvalidUtf8(p) => write!(f, "Invalid UTF-8 at byte {p}"),
+            Self::LimitExceeded(l) => write!(f, "The input exceeds the {l}"),
+            Self::Timeout => write!(f, "Splitting the input did not finish before the deadline"),
+            Self::EmptyKey(p) => write!(f, "Empty key at byte {p}"),
+            Self::UnsplitField(s, p) => write!(f, "The field '{s}' at byte {p} can not be split into a key and a value"),
+            Self::Unencodable(s) => write!(f, "'{s}' can not be encoded with the seperators of the pattern"),
+            Self::MaxDepthExceeded(d) => write!(f, "Values are nested deeper than the maximum total depth of {d}"),
         }
     }
 }
 
 impl std::error::Error for Error {}
 
-#[derive(PartialEq, Debug, Clone, Serialize, Deserialize, Eq)]
+/// Escapes a seperator for use in a pattern string
+fn escape_seperator(sep: &str) -> String {
+    let mut res = String::with_capacity(sep.len());
+    for c in sep.chars() {
+        match c {
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\t' => res.push_str("\\t"),
+            '\r' => res.push_str("\\r"),
+            c => res.push(c),
+        }
+    }
+    res
+}
+
+/// Writes the canonical pattern string of the seperators, e.g.
+/// `%{key}=%{val} %{key}=%{val};` for the field seperators ` ` and `;`.
+/// Options that are not part of the pattern syntax are not included.
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        #[cfg(feature = "regex")]
+        let (key_regex, field_regex) = (
+            self.key_regex.as_ref().map(|re| re.0.as_str()),
+            self.field_regex.as_ref().map(|re| re.0.as_str()),
+        );
+        #[cfg(not(feature = "regex"))]
+        let (key_regex, field_regex) = (None, None);
+        let keys = seperator_texts(&self.key_seperators, key_regex);
+        let fields = seperator_texts(&self.field_seperators, field_regex);
+        let nth =
+            |seps: &[String], i: usize| seps.get(i).or(seps.last()).cloned().unwrap_or_default();
+        for i in 0..keys.len().max(fields.len()).max(1) {
+            write!(f, "%{{key}}{}%{{val}}{}", nth(&keys, i), nth(&fields, i))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for Pattern {
+    type Err = Error;
+
+    fn from_str(pattern: &str) -> Result<Self, Error> {
+        Self::compile(pattern)
+    }
+}
+
+impl TryFrom<&str> for Pattern {
+    type Error = Error;
+
+    fn try_from(pattern: &str) -> Result<Self, Error> {
+        Self::compile(pattern)
+    }
+}
+
+/// The seperators as they are written in a pattern string
+fn seperator_texts(seperators: &[String], regex: Option<&str>) -> Vec<String> {
+    match regex {
+        Some(re) => vec![format!("/re:{re}/")],
+        None => seperators.iter().map(|s| escape_seperator(s)).collect(),
+    }
+}
+
+/// A value type `Pattern::run` can produce, this is implemented for all
+/// values that can be built as and mutated like objects and arrays, e.g.
+/// simd-json's `BorrowedValue` and `OwnedValue`.
+pub trait Output<'input>:
+    ValueBuilder<'input>
+    + MutableObject<Key: Hash + Eq + From<Cow<'input, str>>, Target = Self>
+    + MutableArray<Target = Self>
+    + 'input
+{
+}
+
+impl<'input, V> Output<'input> for V where
+    V: ValueBuilder<'input>
+        + MutableObject<Key: Hash + Eq + From<Cow<'input, str>>, Target = Self>
+        + MutableArray<Target = Self>
+        + 'input
+{
+}
+
+/// How seperators are matched when splitting
+#[derive(PartialEq, Debug, Clone, Copy, Default, Hash, Serialize, Deserialize, Eq)]
+pub enum SplitStrategy {
+    /// the input is split on each seperator in turn (in sorted order), so
+    /// earlier seperators take precedence where seperators overlap
+    #[default]
+    Sequential,
+    /// the input is scanned once from left to right and split at the
+    /// longest seperator matching at the leftmost position
+    LongestMatch,
+}
+
+/// The type the value of a key is expected to have
+#[derive(PartialEq, Debug, Clone, Copy, Hash, Serialize, Deserialize, Eq)]
+pub enum ExpectedType {
+    /// a signed 64 bit integer
+    Integer,
+    /// a 64 bit float
+    Float,
+    /// `true` or `false`
+    Bool,
+}
+
+/// What happens with values that do not match their expected type
+#[derive(PartialEq, Debug, Clone, Copy, Default, Hash, Serialize, Deserialize, Eq)]
+pub enum CoercionFallback {
+    /// the value is kept as a string
+    #[default]
+    AsString,
+    /// the value is replaced with `null`
+    Null,
+    /// the pair is dropped
+    Drop,
+    /// the whole input fails to parse
+    Error,
+}
+
+/// How fields without a key seperator (e.g. `debug` in `debug mode=fast`)
+/// are handled
+#[derive(PartialEq, Debug, Clone, Default, Hash, Serialize, Deserialize, Eq)]
+pub enum FlagPolicy {
+    /// the field is dropped
+    #[default]
+    Drop,
+    /// the field is used as both key and value, `red` becomes `red: "red"`
+    KeyAsValue,
+    /// the field is used as the key of the given value
+    Constant(String),
+    /// the field is used as the key of `true`
+    True,
+    /// the fields are collected in an array under the given key, e.g.
+    /// `debug verbose` becomes `flags: ["debug", "verbose"]`
+    Collect(String),
+}
+
+/// How pairs with an empty value (e.g. `key=`) are handled, fields without
+/// a key seperator are handled by the `FlagPolicy`
+#[derive(PartialEq, Debug, Clone, Copy, Default, Hash, Serialize, Deserialize, Eq)]
+pub enum EmptyValuePolicy {
+    /// the value is an empty string
+    #[default]
+    Keep,
+    /// the pair is dropped
+    Drop,
+    /// the value is `null`
+    Null,
+}
+
+/// How pairs with an empty key (e.g. `=value`) are handled
+#[derive(PartialEq, Debug, Clone, Default, Hash, Serialize, Deserialize, Eq)]
+pub enum EmptyKeyPolicy {
+    /// the key is kept as an empty string
+    #[default]
+    Keep,
+    /// the pair is dropped
+    Drop,
+    /// the whole input is rejected
+    Reject,
+    /// the given key is used instead, e.g. `_empty_key`
+    Rename(String),
+}
+
+/// The characters trimmed from the ends of keys or values
+#[derive(PartialEq, Debug, Clone, Default, Hash, Serialize, Deserialize, Eq)]
+pub enum Trim {
+    /// any whitespace
+    #[default]
+    Whitespace,
+    /// any of the given characters
+    Chars(Vec<char>),
+}
+
+impl Trim {
+    fn trim<'s>(&self, s: &'s str) -> &'s str {
+        match self {
+            Self::Whitespace => s.trim(),
+            Self::Chars(chars) => s.trim_matches(&chars[..]),
+        }
+    }
+}
+
+/// The usual quote characters, `"` and `'`
+pub const DEFAULT_QUOTES: [char; 2] = ['"', '\''];
+
+/// The nesting separator used by `Pattern::with_nested_keys`
+pub const DEFAULT_NESTING_SEPARATOR: &str = ".";
+
+/// The record separator used by `Pattern::run_lines` unless configured
+pub const DEFAULT_RECORD_SEPARATOR: &str = "\n";
+
+/// The default number of levels values are parsed recursively
+pub const DEFAULT_MAX_RECURSION_DEPTH: usize = 10;
+
+/// Bumped whenever the options hashed by `Pattern::fingerprint` change
+const FINGERPRINT_VERSION: u32 = 1;
+
+/// How array elements below the largest index that was set but missing in
+/// the input are handled
+#[derive(PartialEq, Debug, Clone, Copy, Default, Hash, Serialize, Deserialize, Eq)]
+pub enum IndexGapPolicy {
+    /// missing elements are `null`
+    #[default]
+    Null,
+    /// the whole input fails to parse
+    Error,
+}
+
+/// How `Pattern::run_bytes` handles input that is not valid UTF-8
+#[derive(PartialEq, Debug, Clone, Copy, Default, Hash, Serialize, Deserialize, Eq)]
+pub enum Utf8Policy {
+    /// the whole input fails to parse
+    #[default]
+    Error,
+    /// invalid sequences are replaced with `U+FFFD`
+    Lossy,
+    /// pairs with invalid sequences in their key or value are dropped
+    SkipField,
+}
+
+/// A configured limit on the size of an input
+#[derive(PartialEq, Debug, Clone, Copy, Hash, Eq)]
+pub enum Limit {
+    /// the maximum length of the input in bytes
+    InputLength(usize),
+    /// the maximum number of fields
+    Fields(usize),
+    /// the maximum length of a key in bytes
+    KeyLength(usize),
+    /// the maximum length of a value in bytes
+    ValueLength(usize),
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InputLength(n) => write!(f, "maximum input length of {n} bytes"),
+            Self::Fields(n) => write!(f, "maximum of {n} fields"),
+            Self::KeyLength(n) => write!(f, "maximum key length of {n} bytes"),
+            Self::ValueLength(n) => write!(f, "maximum value length of {n} bytes"),
+        }
+    }
+}
+
+/// How repeated keys are handled
+#[derive(PartialEq, Debug, Clone, Copy, Default, Hash, Serialize, Deserialize, Eq)]
+pub enum DuplicatePolicy {
+    /// later values overwrite earlier ones
+    #[default]
+    Overwrite,
+    /// all values of a key are collected into an array in input order,
+    /// like logstash's `allow_duplicate_values`
+    Array,
+    /// the largest value is kept, this is only meaningful for numeric
+    /// values, other values overwrite earlier ones
+    Max,
+    /// the smallest value is kept, this is only meaningful for numeric
+    /// values, other values overwrite earlier ones
+    Min,
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize, Eq, Hash)]
+#[serde(default, remote = "Self")]
+#[allow(clippy::struct_excessive_bools)]
 pub struct Pattern {
-    field_seperators: Vec<String>,
-    key_seperators: Vec<String>,
+    field_seperators: Seperators,
+    key_seperators: Seperators,
+    quotes: Vec<char>,
+    max_distinct_keys: Option<usize>,
+    coerce_values: bool,
+    collapse_consecutive_separators: bool,
+    positional_keys: Vec<String>,
+    duplicates: DuplicatePolicy,
+    value_array_separator: Option<String>,
+    single_value_arrays: bool,
+    value_separator_map: Vec<(String, String)>,
+    #[cfg(feature = "unicode-normalization")]
+    normalize: Option<NormForm>,
+    split_strategy: SplitStrategy,
+    expected_types: BTreeMap<String, ExpectedType>,
+    coercion_fallback: CoercionFallback,
+    leading_token_key: Option<String>,
+    respect_brackets: bool,
+    whitespace_field_separator: bool,
+    strip_trailing_separator: bool,
+    flag_policy: FlagPolicy,
+    empty_values: EmptyValuePolicy,
+    empty_keys: EmptyKeyPolicy,
+    strip_control_chars: bool,
+    strip_control_chars_in_keys: bool,
+    nested_values: bool,
+    escape_char: Option<char>,
+    inner: Option<Box<Pattern>>,
+    trim_first_field: bool,
+    nesting_separator: Option<String>,
+    array_index_notation: bool,
+    index_gaps: IndexGapPolicy,
+    dedupe_identical: bool,
+    whole_as_value_key: Option<String>,
+    max_total_depth: Option<usize>,
+    recursive: bool,
+    keep_quotes: bool,
+    strip_brackets: bool,
+    max_recursion_depth: usize,
+    include_keys: BTreeSet<String>,
+    exclude_keys: BTreeSet<String>,
+    trim_key: Option<Trim>,
+    trim_value: Option<Trim>,
+    key_prefix: Option<String>,
+    split_once: bool,
+    strict_logfmt: bool,
+    strict: bool,
+    percent_decode: bool,
+    invalid_utf8: Utf8Policy,
+    record_separator: String,
+    max_pairs: Option<usize>,
+    max_input_length: Option<usize>,
+    max_fields: Option<usize>,
+    max_key_length: Option<usize>,
+    max_value_length: Option<usize>,
+    #[cfg(feature = "chrono")]
+    coerce_timestamps: bool,
+    #[cfg(feature = "regex")]
+    field_regex: Option<RegexSeperator>,
+    #[cfg(feature = "regex")]
+    key_regex: Option<RegexSeperator>,
 }
 
 impl std::default::Default for Pattern {
     fn default() -> Self {
         Self {
-            field_seperators: vec![" ".to_string()],
-            key_seperators: vec![":".to_string()],
+            field_seperators: vec![" ".to_string()].into(),
+            key_seperators: vec![":".to_string()].into(),
+            quotes: Vec::new(),
+            max_distinct_keys: None,
+            coerce_values: false,
+            collapse_consecutive_separators: false,
+            positional_keys: Vec::new(),
+            duplicates: DuplicatePolicy::Overwrite,
+            value_array_separator: None,
+            single_value_arrays: false,
+            value_separator_map: Vec::new(),
+            #[cfg(feature = "unicode-normalization")]
+            normalize: None,
+            split_strategy: SplitStrategy::Sequential,
+            expected_types: BTreeMap::new(),
+            coercion_fallback: CoercionFallback::AsString,
+            leading_token_key: None,
+            respect_brackets: false,
+            whitespace_field_separator: false,
+            strip_trailing_separator: false,
+            flag_policy: FlagPolicy::Drop,
+            empty_values: EmptyValuePolicy::Keep,
+            empty_keys: EmptyKeyPolicy::Keep,
+            strip_control_chars: false,
+            strip_control_chars_in_keys: false,
+            nested_values: false,
+            escape_char: None,
+            inner: None,
+            trim_first_field: false,
+            nesting_separator: None,
+            array_index_notation: false,
+            index_gaps: IndexGapPolicy::Null,
+            dedupe_identical: false,
+            whole_as_value_key: None,
+            max_total_depth: None,
+            recursive: false,
+            keep_quotes: false,
+            strip_brackets: false,
+            max_recursion_depth: DEFAULT_MAX_RECURSION_DEPTH,
+            include_keys: BTreeSet::new(),
+            exclude_keys: BTreeSet::new(),
+            trim_key: None,
+            trim_value: None,
+            key_prefix: None,
+            split_once: false,
+            strict_logfmt: false,
+            strict: false,
+            percent_decode: false,
+            invalid_utf8: Utf8Policy::Error,
+            record_separator: DEFAULT_RECORD_SEPARATOR.to_string(),
+            max_pairs: None,
+            max_input_length: None,
+            max_fields: None,
+            max_key_length: None,
+            max_value_length: None,
+            #[cfg(feature = "chrono")]
+            coerce_timestamps: false,
+            #[cfg(feature = "regex")]
+            field_regex: None,
+            #[cfg(feature = "regex")]
+            key_regex: None,
+        }
+    }
+}
+
+/// Patterns serialize as their pattern string when it expresses all their
+/// options, otherwise as their options.
+impl Serialize for Pattern {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match pattern_string::to_pattern_string(self) {
+            Ok(s) => serializer.serialize_str(&s),
+            Err(_) => Pattern::serialize(self, serializer),
+        }
+    }
+}
+
+/// Patterns deserialize from a pattern string or from their options, the
+/// seperators are validated like with `compile` either way.
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        #[allow(clippy::large_enum_variant)]
+        enum Repr {
+            Pattern(String),
+            Options(#[serde(with = "Pattern")] Pattern),
         }
+        match Repr::deserialize(deserializer)? {
+            Repr::Pattern(pattern) => Self::compile(&pattern),
+            Repr::Options(pattern) => pattern.validated(),
+        }
+        .map_err(serde::de::Error::custom)
     }
 }
 
@@ -122,9 +628,56 @@ fn handle_escapes(s: &str) -> Result<String, Error> {
 
 impl Pattern {
     /// compiles a pattern
+    ///
+    /// A pattern is made up of `%{key}<key seperator>%{val}` groups, the
+    /// text between groups are field seperators. Seperators that are not
+    /// given default to a space for fields and `:` for keys, so the empty
+    /// pattern is the same as `%{key}:%{val}` and a pattern made up only of
+    /// seperators (e.g. `&`) declares field seperators.
+    ///
+    /// With the `regex` feature seperators can be regular expressions
+    /// written as `/re:<expression>/` in a pattern with a single group,
+    /// e.g. `%{key}/re:\s*=\s*/%{val}/re:\s+/`.
+    ///
     /// # Errors
-    /// fails if the pattern is invalid
+    /// fails if the pattern is invalid, that is a `%{key}` without a `%{val}`
+    /// (e.g. `%{key}`), a `%{val}` without a `%{key}` before it (e.g.
+    /// `%{val}` or `%{val}%{key}`) or a `%{key}` directly followed by a
+    /// `%{val}` as there is no seperator between them
     pub fn compile(pattern: &str) -> Result<Self, Error> {
+        #[cfg(feature = "regex")]
+        if pattern.contains("/re:") {
+            return Self::compile_regex_syntax(pattern, false);
+        }
+        let (field_seperators, key_seperators) = Self::pattern_seperators(pattern)?;
+        Self::from_seperators(field_seperators, key_seperators)
+    }
+
+    /// compiles a pattern like `compile` but fails instead of using the
+    /// default for seperators the pattern does not declare, so e.g.
+    /// `%{key}=%{val}` has to be written as `%{key}=%{val} ` to declare the
+    /// space as field seperator.
+    ///
+    /// # Errors
+    /// fails if the pattern is invalid or declares no field seperator or no
+    /// key seperator
+    pub fn compile_strict(pattern: &str) -> Result<Self, Error> {
+        #[cfg(feature = "regex")]
+        if pattern.contains("/re:") {
+            return Self::compile_regex_syntax(pattern, true);
+        }
+        let (field_seperators, key_seperators) = Self::pattern_seperators(pattern)?;
+        if field_seperators.is_empty() {
+            return Err(Error::NoFieldSeperator(pattern.to_string()));
+        }
+        if key_seperators.is_empty() {
+            return Err(Error::NoKeySeperator(pattern.to_string()));
+        }
+        Self::from_seperators(field_seperators, key_seperators)
+    }
+
+    /// Parses the field and key seperators out of a pattern
+    fn pattern_seperators(pattern: &str) -> Result<(Vec<String>, Vec<String>), Error> {
         let mut field_seperators = Vec::new();
         let mut key_seperators = Vec::new();
         let mut i = 0;
@@ -132,25 +685,84 @@ impl Pattern {
             if pattern[i..].starts_with("%{key}") {
                 i += 6;
                 if let Some(i1) = pattern[i..].find("%{val}") {
-                    if i1 != 0 {
-                        key_seperators.push(handle_escapes(&pattern[i..i + i1])?);
+                    if i1 == 0 {
+                        return Err(Error::MissingKeySeperator(i));
                     }
+                    key_seperators.push(handle_escapes(&pattern[i..i + i1])?);
                     i += i1 + 6;
                 } else {
                     return Err(Error::InvalidPattern(i));
                 }
-            } else if let Some(i1) = pattern[i..].find("%{key}") {
-                if i1 != 0 {
-                    field_seperators.push(handle_escapes(&pattern[i..i + i1])?);
-                }
-                i += i1;
-            } else if pattern[i..].is_empty() {
-                break;
             } else {
-                field_seperators.push(handle_escapes(&pattern[i..])?);
-                break;
+                let end = pattern[i..]
+                    .find("%{key}")
+                    .map_or(pattern.len(), |i1| i + i1);
+                if let Some(v) = pattern[i..end].find("%{val}") {
+                    return Err(Error::UnexpectedVal(i + v));
+                }
+                if end != i {
+                    field_seperators.push(handle_escapes(&pattern[i..end])?);
+                }
+                if end == pattern.len() {
+                    break;
+                }
+                i = end;
             }
         }
+        Ok((field_seperators, key_seperators))
+    }
+
+    /// compiles a pattern from a header line that declares the seperators
+    ///
+    /// The header starts with `#kv` followed by space separated
+    /// settings of the form `name=value`:
+    ///
+    /// * `field_sep=<seperator>` - adds a field seperator
+    /// * `key_sep=<seperator>` - adds a key value seperator
+    ///
+    /// Settings can be repeated to add multiple seperators. Values support
+    /// the same escapes as patterns and have to be double quoted if they
+    /// contain a space or `=` (e.g. `field_sep=" "`). Seperators that are not declared use the same defaults as
+    /// `compile`, e.g. `#kv field_sep=, key_sep=:`.
+    ///
+    /// # Errors
+    /// fails if the header is malformed or declares invalid seperators
+    pub fn compile_from_header(header_line: &str) -> Result<Self, Error> {
+        let settings = header_line
+            .trim_end()
+            .strip_prefix("#kv")
+            .filter(|s| s.is_empty() || s.starts_with(' '))
+            .ok_or_else(|| Error::InvalidHeader(header_line.to_string()))?;
+        let header = Self::compile("%{key}=%{val}")?.with_quotes(&['"']);
+        let mut field_seperators = Vec::new();
+        let mut key_seperators = Vec::new();
+        let mut valid = true;
+        let pairs = header.extract_with(settings, |_, _| valid = false);
+        if !valid {
+            return Err(Error::InvalidHeader(header_line.to_string()));
+        }
+        for Pair {
+            key: name,
+            val: sep,
+            ..
+        } in pairs
+        {
+            let sep = handle_escapes(&sep)?;
+            match &*name {
+                _ if sep.is_empty() => return Err(Error::InvalidHeader(name.to_string())),
+                "field_sep" => field_seperators.push(sep),
+                "key_sep" => key_seperators.push(sep),
+                other => return Err(Error::InvalidHeader(other.to_string())),
+            }
+        }
+        Self::from_seperators(field_seperators, key_seperators)
+    }
+
+    /// Validates the seperators and builds a pattern from them
+    fn from_seperators(
+        mut field_seperators: Vec<String>,
+        mut key_seperators: Vec<String>,
+    ) -> Result<Self, Error> {
         if field_seperators.is_empty() {
             field_seperators.push(" ".to_string());
         }
@@ -164,226 +776,4233 @@ impl Pattern {
 
         for fs in &field_seperators {
             if key_seperators.iter().any(|ks| ks.contains(fs)) {
-                return Err(Error::DoubleSeperator(fs.to_string()));
+                return Err(Error::DoubleSeperator(fs.clone()));
             }
 
             if field_seperators
                 .iter()
                 .any(|fs2| fs2 != fs && fs2.contains(fs))
             {
-                return Err(Error::DoubleSeperator(fs.to_string()));
+                return Err(Error::DoubleSeperator(fs.clone()));
             }
         }
 
         for ks in &key_seperators {
             if field_seperators.iter().any(|fs| fs.contains(ks)) {
-                return Err(Error::DoubleSeperator(ks.to_string()));
+                return Err(Error::DoubleSeperator(ks.clone()));
             }
 
             if key_seperators
                 .iter()
                 .any(|ks2| ks2 != ks && ks2.contains(ks))
             {
-                return Err(Error::DoubleSeperator(ks.to_string()));
+                return Err(Error::DoubleSeperator(ks.clone()));
             }
         }
 
         Ok(Self {
-            field_seperators,
-            key_seperators,
+            field_seperators: field_seperators.into(),
+            key_seperators: key_seperators.into(),
+            ..Self::default()
         })
     }
-    /// Splits a string that represents KV pairs.
-    ///
-    /// * `input` - The input string
-    ///
-    /// Note: Fields that have on value are dropped.
-    pub fn run<'input, V>(&self, input: &'input str) -> Option<V>
-    where
-        V: ValueBuilder<'input> + MutableObject + 'input,
-        <V as MutableObject>::Key: std::hash::Hash + Eq + From<&'input str>,
-        <V as MutableObject>::Target: std::convert::From<&'input str>,
-    {
-        let mut r = V::object();
-        let mut empty = true;
-        for field in multi_split(input, &self.field_seperators) {
-            let kv: Vec<&str> = multi_split(field, &self.key_seperators);
-            if kv.len() == 2 {
-                empty = false;
-                r.insert(kv[0], kv[1]).ok()?;
-            }
-        }
-        if empty {
-            None
-        } else {
-            Some(r)
-        }
+
+    /// Sets the characters that quote keys and values, e.g.
+    /// `DEFAULT_QUOTES`. Separators inside a quoted span are ignored and
+    /// the surrounding quotes are removed from keys and values that are
+    /// quoted as a whole.
+    #[must_use]
+    pub fn with_quotes(mut self, quotes: &[char]) -> Self {
+        self.quotes = quotes.to_vec();
+        self
     }
-}
 
-fn multi_split<'input>(input: &'input str, seperators: &[String]) -> Vec<&'input str> {
-    use std::mem;
-    let mut i: Vec<&str> = vec![input];
-    let mut i1 = vec![];
-    let mut r: Vec<&str>;
-    for s in seperators {
-        i1.clear();
-        for e in &i {
-            r = e.split(s.as_str()).collect();
-            i1.append(&mut r);
-        }
-        mem::swap(&mut i, &mut i1);
+    /// Keeps the surrounding quotes of quoted values, keys are unquoted
+    /// regardless.
+    #[must_use]
+    pub fn with_keep_quotes(mut self, keep: bool) -> Self {
+        self.keep_quotes = keep;
+        self
     }
-    i
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
-    use simd_json::borrowed::Object;
-    use simd_json::BorrowedValue;
+    /// Limits the number of distinct keys in the output, once the limit is
+    /// reached pairs with new keys are dropped while pairs for keys that are
+    /// already present still update them.
+    #[must_use]
+    pub fn with_max_distinct_keys(mut self, max: usize) -> Self {
+        self.max_distinct_keys = Some(max);
+        self
+    }
 
-    #[test]
-    fn default() {
-        let d = Pattern::default();
-        let p = Pattern::compile("%{key}:%{val}").expect("compile");
-        assert_eq!(d, p);
+    /// Turns values that look like integers, floats, booleans or `null`
+    /// into typed values instead of keeping them as strings. Coercion only
+    /// applies to values, keys are always strings (`true=false` has the key
+    /// `"true"`). Floats that are out of range, e.g. `1e999`, stay strings.
+    #[must_use]
+    pub fn with_coerce_values(mut self, coerce: bool) -> Self {
+        self.coerce_values = coerce;
+        self
     }
-    #[test]
-    fn test_multisplit() {
-        let seps = vec![String::from(" "), String::from(";")];
-        let input = "this=is;a=test for:seperators";
 
-        let i = multi_split(input, &seps);
-        assert_eq!(i, vec!["this=is", "a=test", "for:seperators"]);
+    /// Declares the type the values of `key` are expected to have, they are
+    /// coerced into it regardless of `with_coerce_values` and handled
+    /// according to the coercion fallback if they do not match it.
+    #[must_use]
+    pub fn with_expected_type(mut self, key: &str, expected: ExpectedType) -> Self {
+        self.expected_types.insert(key.to_string(), expected);
+        self
     }
-    #[test]
 
-    fn simple_split() {
-        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
-        let r: BorrowedValue = kv.run("this=is a=test").expect("Failed to split input");
-        assert_eq!(r.as_object().map(Object::len).unwrap_or_default(), 2);
-        assert_eq!(r["this"], "is");
-        assert_eq!(r["a"], "test");
+    /// Sets how values that do not match their expected type are handled,
+    /// by default they are kept as strings.
+    #[must_use]
+    pub fn with_coercion_fallback(mut self, fallback: CoercionFallback) -> Self {
+        self.coercion_fallback = fallback;
+        self
     }
 
-    #[test]
-    fn simple_split2() {
-        let kv = Pattern::compile("&%{key}=%{val}").expect("Failed to build pattern");
-        let r: BorrowedValue = kv.run("this=is&a=test").expect("Failed to split input");
-        assert_eq!(r.as_object().map(Object::len).unwrap_or_default(), 2);
-        assert_eq!(r["this"], "is");
-        assert_eq!(r["a"], "test");
+    /// Treats runs of the same seperator as a single seperator, so `a==b`
+    /// splits into `a` and `b` when `=` is the key seperator.
+    #[must_use]
+    pub fn with_collapse_consecutive_separators(mut self, collapse: bool) -> Self {
+        self.collapse_consecutive_separators = collapse;
+        self
     }
-    #[test]
-    fn newline_simple_() {
-        let kv = Pattern::compile(r"\n%{key}=%{val}").expect("Failed to build pattern");
-        let r: BorrowedValue = kv.run("this=is\na=test").expect("Failed to split input");
-        assert_eq!(r.as_object().map(Object::len).unwrap_or_default(), 2);
-        assert_eq!(r["this"], "is");
-        assert_eq!(r["a"], "test");
+
+    /// Names leading fields that have no key seperator, for inputs like
+    /// `foo bar k=v` the fields `foo` and `bar` are stored under the given
+    /// keys in order. Positional capture ends with the first key value pair,
+    /// leading fields beyond the number of keys are dropped.
+    #[must_use]
+    pub fn with_positional_keys(mut self, keys: &[&str]) -> Self {
+        self.positional_keys = keys.iter().map(ToString::to_string).collect();
+        self
     }
 
-    #[test]
-    fn simple_split3() {
-        let kv = Pattern::compile("&").expect("Failed to build pattern");
-        let r: BorrowedValue = kv.run("this:is&a:test").expect("Failed to split input");
-        assert_eq!(r.as_object().map(Object::len).unwrap_or_default(), 2);
-        assert_eq!(r["this"], "is");
-        assert_eq!(r["a"], "test");
+    /// Only keeps pairs with one of the given keys, all other pairs are
+    /// skipped while parsing instead of being inserted. With no keys given
+    /// every key is kept.
+    #[must_use]
+    pub fn with_include_keys(mut self, keys: &[&str]) -> Self {
+        self.include_keys = keys.iter().map(ToString::to_string).collect();
+        self
     }
 
-    #[test]
-    fn simple_split4() {
-        let kv = Pattern::compile("%{key}%{%{val}").expect("Failed to build pattern");
-        let r: BorrowedValue = kv.run("this%{is a%{test").expect("Failed to split input");
-        assert_eq!(r.as_object().map(Object::len).unwrap_or_default(), 2);
-        assert_eq!(r["this"], "is");
-        assert_eq!(r["a"], "test");
+    /// Skips pairs with any of the given keys while parsing.
+    #[must_use]
+    pub fn with_exclude_keys(mut self, keys: &[&str]) -> Self {
+        self.exclude_keys = keys.iter().map(ToString::to_string).collect();
+        self
     }
 
-    #[test]
-    fn simple_split5() {
-        let kv = Pattern::compile("%{key}%{key}%{val}").expect("Failed to build pattern");
-        dbg!(&kv);
-        let r: BorrowedValue = kv
-            .run("this%{key}is a%{key}test")
-            .expect("Failed to split input");
-        assert_eq!(r.as_object().map(Object::len).unwrap_or_default(), 2);
-        assert_eq!(r["this"], "is");
-        assert_eq!(r["a"], "test");
+    /// Trims the given characters from both ends of every key, so
+    /// `key = value` with a `=` key seperator has the key `key`.
+    #[must_use]
+    pub fn with_trim_key(mut self, trim: Trim) -> Self {
+        self.trim_key = Some(trim);
+        self
     }
 
-    #[test]
-    fn invalid_pattern() {
-        let kv = Pattern::compile("%{key} ");
-        let e = kv.expect_err("no error");
-        assert_eq!(e, Error::InvalidPattern(6));
-        println!("{e}");
+    /// Trims the given characters from both ends of every value.
+    #[must_use]
+    pub fn with_trim_value(mut self, trim: Trim) -> Self {
+        self.trim_value = Some(trim);
+        self
+    }
 
-        let kv = Pattern::compile("%{key} %{val} \\8");
-        let e = kv.expect_err("no error");
-        assert_eq!(e, Error::InvalidEscape('8'));
-        println!("{e}");
+    /// Prepends `prefix` to every key, `a=1` becomes `kv_a: 1` with the
+    /// prefix `kv_`. Include and exclude keys refer to the keys without the
+    /// prefix.
+    #[must_use]
+    pub fn with_key_prefix(mut self, prefix: &str) -> Self {
+        self.key_prefix = Some(prefix.to_string());
+        self
+    }
 
-        let kv = Pattern::compile("%{key} %{val} ");
-        let e = kv.expect_err("no error");
-        assert_eq!(e, Error::DoubleSeperator(String::from(" ")));
-        println!("{e}");
+    /// Stores everything up to the first run of whitespace under `key` and
+    /// splits only the rest of the input into pairs, e.g. the app name in
+    /// `myapp user=bob`. The leading token is taken as is even if it
+    /// contains seperators.
+    #[must_use]
+    pub fn with_leading_token_key(mut self, key: &str) -> Self {
+        self.leading_token_key = Some(key.to_string());
+        self
+    }
 
-        let kv = Pattern::compile("%{key}=%{val}; %{key}:%{val} %{key}:%{val}");
-        let e = kv.expect_err("no error");
-        assert_eq!(e, Error::DoubleSeperator(String::from(" ")));
-        println!("{e}");
+    /// Ignores seperators inside balanced `()`, `[]`, `{}` and `<>` so
+    /// values like `func(a, b)` are not split, unbalanced brackets are
+    /// taken literally.
+    #[must_use]
+    pub fn with_respect_brackets(mut self, respect: bool) -> Self {
+        self.respect_brackets = respect;
+        self
+    }
 
-        let kv = Pattern::compile("%{key}=%{val};%{key}:%{val} :%{key}:%{val}");
-        let e = kv.expect_err("no error");
-        assert_eq!(e, Error::DoubleSeperator(String::from(":")));
-        println!("{e}");
+    /// Removes the brackets around values that are bracketed as a whole
+    /// with `with_respect_brackets`, so `list=[1, 2]` yields `1, 2`.
+    #[must_use]
+    pub fn with_strip_brackets(mut self, strip: bool) -> Self {
+        self.strip_brackets = strip;
+        self
     }
-    #[test]
-    fn one_field() {
-        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
-        let r: BorrowedValue = kv.run("this=is").expect("Failed to split input");
-        assert_eq!(r.as_object().map(Object::len).unwrap_or_default(), 1);
-        assert_eq!(r["this"], "is");
+
+    /// Splits fields at every run of whitespace (e.g. mixed tabs and
+    /// spaces) in addition to the field seperators.
+    #[must_use]
+    pub fn with_whitespace_field_separator(mut self, whitespace: bool) -> Self {
+        self.whitespace_field_separator = whitespace;
+        self
     }
 
-    #[test]
-    fn no_split() {
-        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
-        let r: Option<BorrowedValue> = kv.run("this is a test");
-        assert!(r.is_none());
+    /// Strips key seperators from the start and end of values that are
+    /// wrapped in them, e.g. `k==val==` becomes `k` and `val` instead of
+    /// being dropped. Values that still contain a key seperator after
+    /// stripping are dropped.
+    #[must_use]
+    pub fn with_strip_trailing_separator(mut self, strip: bool) -> Self {
+        self.strip_trailing_separator = strip;
+        self
     }
 
-    #[test]
-    fn different_seperatpors() {
-        let kv = Pattern::compile("%{key}=%{val};%{key}:%{val} %{key}:%{val}")
-            .expect("Failed to build pattern");
-        dbg!(&kv);
-        let r: BorrowedValue = kv
-            .run("this=is;a=test for:seperators")
-            .expect("Failed to split input");
-        dbg!(&r);
-        assert_eq!(r.as_object().map(Object::len).unwrap_or_default(), 3);
-        assert_eq!(r["this"], "is");
-        assert_eq!(r["a"], "test");
-        assert_eq!(r["for"], "seperators");
+    /// Splits fields at the first key seperator only and keeps the rest,
+    /// including further key seperators, in the value. By default fields
+    /// with more than one key seperator like `url=http://host:8080` with
+    /// `:` and `=` seperators are dropped.
+    #[must_use]
+    pub fn with_split_once(mut self, once: bool) -> Self {
+        self.split_once = once;
+        self
     }
 
-    #[test]
-    fn different_seperatpors2() {
-        let kv = Pattern::compile("%{key}=%{val}%{key}:%{val} %{key}:%{val};")
-            .expect("Failed to build pattern");
+    /// Rejects the whole input if any field is not well formed logfmt, e.g.
+    /// has an unterminated quote, an empty key or more than one `=`
+    /// outside of quotes. The malformed field is reported with
+    /// `DropReason::Malformed`.
+    #[must_use]
+    pub fn with_strict_logfmt(mut self, strict: bool) -> Self {
+        self.strict_logfmt = strict;
+        self
+    }
+
+    /// Rejects the whole input if any field can not be split into a key and
+    /// a value, i.e. it has no or too many key seperators. Fields that are
+    /// kept as flags or stored under positional keys are split.
+    #[must_use]
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Percent decodes keys and values with `+` as space, like in URL query
+    /// strings, so `a=hello%20world` has the value `hello world`.
+    #[must_use]
+    pub fn with_percent_decode(mut self, decode: bool) -> Self {
+        self.percent_decode = decode;
+        self
+    }
+
+    /// Stops splitting the input once `max` pairs were found, the rest of
+    /// the input is ignored. Pairs dropped by the include or exclude keys
+    /// count towards the limit as well.
+    #[must_use]
+    pub fn with_max_pairs(mut self, max: usize) -> Self {
+        self.max_pairs = Some(max);
+        self
+    }
+
+    /// Rejects inputs longer than `max` bytes without splitting them
+    #[must_use]
+    pub fn with_max_input_length(mut self, max: usize) -> Self {
+        self.max_input_length = Some(max);
+        self
+    }
+
+    /// Rejects inputs with more than `max` fields, empty fields between
+    /// consecutive field separators count as well.
+    #[must_use]
+    pub fn with_max_fields(mut self, max: usize) -> Self {
+        self.max_fields = Some(max);
+        self
+    }
+
+    /// Rejects inputs with a key longer than `max` bytes
+    #[must_use]
+    pub fn with_max_key_length(mut self, max: usize) -> Self {
+        self.max_key_length = Some(max);
+        self
+    }
+
+    /// Rejects inputs with a value longer than `max` bytes
+    #[must_use]
+    pub fn with_max_value_length(mut self, max: usize) -> Self {
+        self.max_value_length = Some(max);
+        self
+    }
+
+    /// Sets the separator between records used by `run_lines`, by default
+    /// records are lines. With an empty separator the whole input is one
+    /// record.
+    #[must_use]
+    pub fn with_record_separator(mut self, separator: &str) -> Self {
+        self.record_separator = separator.to_string();
+        self
+    }
+
+    /// Sets how `run_bytes` handles input that is not valid UTF-8, by
+    /// default it fails.
+    #[must_use]
+    pub fn with_invalid_utf8(mut self, policy: Utf8Policy) -> Self {
+        self.invalid_utf8 = policy;
+        self
+    }
+
+    /// Sets how fields without a key seperator are handled, by default they
+    /// are dropped. Positional keys take precedence over flags. Collected
+    /// flags replace a pair with the same key.
+    #[must_use]
+    pub fn with_flag_policy(mut self, policy: FlagPolicy) -> Self {
+        self.flag_policy = policy;
+        self
+    }
+
+    /// Sets how pairs with an empty value are handled, by default the value
+    /// is an empty string. Values are empty after trimming and unquoting,
+    /// so `key=""` has an empty value as well.
+    #[must_use]
+    pub fn with_empty_values(mut self, policy: EmptyValuePolicy) -> Self {
+        self.empty_values = policy;
+        self
+    }
+
+    /// Sets how pairs with an empty key are handled, by default the key is
+    /// kept. Keys are empty after trimming and unquoting. Dropped and
+    /// rejected pairs are reported with `DropReason::EmptyKey`.
+    #[must_use]
+    pub fn with_empty_keys(mut self, policy: EmptyKeyPolicy) -> Self {
+        self.empty_keys = policy;
+        self
+    }
+
+    /// Removes control characters and unicode bidi controls (e.g. the
+    /// right-to-left override) from values, this guards against log
+    /// injection and text that displays differently than it reads.
+    #[must_use]
+    pub fn with_strip_control_chars(mut self, strip: bool) -> Self {
+        self.strip_control_chars = strip;
+        self
+    }
+
+    /// Removes control characters and unicode bidi controls from keys like
+    /// `with_strip_control_chars` does for values.
+    #[must_use]
+    pub fn with_strip_control_chars_in_keys(mut self, strip: bool) -> Self {
+        self.strip_control_chars_in_keys = strip;
+        self
+    }
+
+    /// Turns fields with two key seperators like `meta=type:error` into a
+    /// single entry object (`{"meta": {"type": "error"}}`) instead of
+    /// dropping them. The field is split at the first key seperator and the
+    /// rest at the second, fields with more key seperators are still
+    /// dropped. This takes precedence over `with_strip_trailing_separator`,
+    /// raw values (e.g. of `get_nth`) are the unsplit rest.
+    #[must_use]
+    pub fn with_nested_values(mut self, nested: bool) -> Self {
+        self.nested_values = nested;
+        self
+    }
+
+    /// Sets a character that escapes the character following it, escaped
+    /// seperators do not split (e.g. `a\=b=c` with `\` has the key `a=b`)
+    /// and the escape characters are removed from keys and values. Raw
+    /// values (e.g. of `get_nth`) keep the escapes.
+    #[must_use]
+    pub fn with_escape_char(mut self, escape: char) -> Self {
+        self.escape_char = Some(escape);
+        self
+    }
+
+    /// Parses the values split by this pattern with `inner`, values that
+    /// `inner` extracts pairs from become nested objects while all other
+    /// values are kept. Patterns can be chained to parse more levels.
+    #[must_use]
+    pub fn then(mut self, inner: Pattern) -> Self {
+        self.inner = Some(Box::new(inner));
+        self
+    }
+
+    /// Trims a leading byte order mark and whitespace from the first field
+    /// only, so they do not end up in the first key.
+    #[must_use]
+    pub fn with_trim_first_field(mut self, trim: bool) -> Self {
+        self.trim_first_field = trim;
+        self
+    }
+
+    /// Splits keys at `separator` into paths of nested objects, e.g. with
+    /// `.` the key `http.status` becomes `{"http": {"status": ...}}`. Later
+    /// pairs replace values on their path that are not objects. An empty
+    /// separator turns nesting off.
+    #[must_use]
+    pub fn with_nesting_separator(mut self, separator: &str) -> Self {
+        self.nesting_separator = (!separator.is_empty()).then(|| separator.to_string());
+        self
+    }
+
+    /// Turns nesting of dotted keys on or off, this is the same as
+    /// `with_nesting_separator(DEFAULT_NESTING_SEPARATOR)` when enabled.
+    #[must_use]
+    pub fn with_nested_keys(mut self, nested: bool) -> Self {
+        self.nesting_separator = nested.then(|| DEFAULT_NESTING_SEPARATOR.to_string());
+        self
+    }
+
+    /// Makes path segments that are numbers (up to 65535) indices into
+    /// arrays instead of object keys when keys are nested, so `a.0=x a.1=y`
+    /// becomes `{"a": ["x", "y"]}`.
+    #[must_use]
+    pub fn with_array_index_notation(mut self, arrays: bool) -> Self {
+        self.array_index_notation = arrays;
+        self
+    }
+
+    /// Sets how elements missing below the largest index set in an array
+    /// are handled, by default they are `null`.
+    #[must_use]
+    pub fn with_index_gaps(mut self, gaps: IndexGapPolicy) -> Self {
+        self.index_gaps = gaps;
+        self
+    }
+
+    /// Collapses pairs repeating an earlier pair with the same key and value
+    /// into one, so only differing values are handled by the duplicate
+    /// policy.
+    #[must_use]
+    pub fn with_dedupe_identical(mut self, dedupe: bool) -> Self {
+        self.dedupe_identical = dedupe;
+        self
+    }
+
+    /// Stores an input no pairs can be split from as a single value under
+    /// `key`, so a plain message like `service started` yields
+    /// `{"message": "service started"}` for `message` instead of nothing.
+    #[must_use]
+    pub fn with_whole_as_value_key(mut self, key: &str) -> Self {
+        self.whole_as_value_key = Some(key.to_string());
+        self
+    }
+
+    /// Limits how many levels values can be nested below the top level
+    /// object, counting nested values, recursively parsed values as well as
+    /// every pattern composed with `then`. The limit of the outermost pattern applies to all
+    /// levels, inputs nesting deeper fail to parse.
+    #[must_use]
+    pub fn with_max_total_depth(mut self, depth: usize) -> Self {
+        self.max_total_depth = Some(depth);
+        self
+    }
+
+    /// Parses values that match the pattern themselves (see `matches`)
+    /// into nested objects, e.g. `outer="a:1,b:2"` becomes
+    /// `{"outer": {"a": "1", "b": "2"}}` for `%{key}=%{val},%{key}:%{val}`
+    /// with `"` as quote.
+    #[must_use]
+    pub fn with_recursive(mut self, recursive: bool) -> Self {
+        self.recursive = recursive;
+        self
+    }
+
+    /// Limits how many levels deep values are parsed recursively, values
+    /// below are kept as they are, it defaults to
+    /// `DEFAULT_MAX_RECURSION_DEPTH`.
+    #[must_use]
+    pub fn with_max_recursion_depth(mut self, depth: usize) -> Self {
+        self.max_recursion_depth = depth;
+        self
+    }
+
+    /// Turns values that are RFC 3339 timestamps (e.g.
+    /// `2020-01-01T00:00:00Z`) into milliseconds since the unix epoch,
+    /// other values are left as they are.
+    #[cfg(feature = "chrono")]
+    #[must_use]
+    pub fn with_coerce_timestamps(mut self, coerce: bool) -> Self {
+        self.coerce_timestamps = coerce;
+        self
+    }
+
+    /// Sets how keys that appear more than once are handled, by default
+    /// later values overwrite earlier ones.
+    #[must_use]
+    pub fn with_duplicates(mut self, duplicates: DuplicatePolicy) -> Self {
+        self.duplicates = duplicates;
+        self
+    }
+
+    /// Splits values on the given seperator into arrays, so `tags=a,b,c`
+    /// yields `["a", "b", "c"]` for `,`. Values that don't contain the
+    /// seperator stay scalars unless `with_single_value_arrays` is set. An
+    /// empty seperator turns splitting values off.
+    #[must_use]
+    pub fn with_value_array_separator(mut self, separator: &str) -> Self {
+        self.value_array_separator = (!separator.is_empty()).then(|| separator.to_string());
+        self
+    }
+
+    /// Sets if values without the value array seperator are wrapped in a
+    /// single element array so a key always holds an array.
+    #[must_use]
+    pub fn with_single_value_arrays(mut self, wrap: bool) -> Self {
+        self.single_value_arrays = wrap;
+        self
+    }
+
+    /// Replaces `from` with `to` in values before they are split into
+    /// arrays, so mixed list seperators like `a;b,c` can be normalized to
+    /// the value array seperator. Mappings are applied in the order they
+    /// are added, mappings from an empty string are ignored.
+    #[must_use]
+    pub fn with_value_separator_mapping(mut self, from: &str, to: &str) -> Self {
+        if !from.is_empty() {
+            self.value_separator_map
+                .push((from.to_string(), to.to_string()));
+        }
+        self
+    }
+
+    /// Normalizes keys and values to the given unicode normalization form
+    /// before they are inserted, so differently normalized keys merge.
+    #[cfg(feature = "unicode-normalization")]
+    #[must_use]
+    pub fn with_normalize(mut self, form: NormForm) -> Self {
+        self.normalize = Some(form);
+        self
+    }
+
+    /// Sets how seperators are matched, this only makes a difference for
+    /// overlapping seperators. The default is `SplitStrategy::Sequential`.
+    #[must_use]
+    pub fn with_split_strategy(mut self, strategy: SplitStrategy) -> Self {
+        self.split_strategy = strategy;
+        self
+    }
+
+    /// Checks the seperators of a pattern that was not compiled, they are
+    /// sorted and defaulted like with `compile`
+    fn validated(self) -> Result<Self, Error> {
+        #[cfg(feature = "regex")]
+        let (field_regex, key_regex) = (self.field_regex.is_some(), self.key_regex.is_some());
+        #[cfg(not(feature = "regex"))]
+        let (field_regex, key_regex) = (false, false);
+        let seperators =
+            Self::from_seperators(self.field_seperators.to_vec(), self.key_seperators.to_vec())?;
+        Ok(Self {
+            field_seperators: if field_regex {
+                Seperators::default()
+            } else {
+                seperators.field_seperators
+            },
+            key_seperators: if key_regex {
+                Seperators::default()
+            } else {
+                seperators.key_seperators
+            },
+            value_array_separator: self.value_array_separator.filter(|s| !s.is_empty()),
+            nesting_separator: self.nesting_separator.filter(|s| !s.is_empty()),
+            value_separator_map: self
+                .value_separator_map
+                .into_iter()
+                .filter(|(from, _)| !from.is_empty())
+                .collect(),
+            ..self
+        })
+    }
+
+    /// The literal field seperators, empty if fields are split with a
+    /// regular expression
+    pub fn field_separators(&self) -> &[String] {
+        &self.field_seperators
+    }
+
+    /// The literal key seperators, empty if keys are split with a regular
+    /// expression
+    pub fn key_separators(&self) -> &[String] {
+        &self.key_seperators
+    }
+
+    /// The quote characters
+    pub fn quotes(&self) -> &[char] {
+        &self.quotes
+    }
+
+    /// A stable fingerprint of the compiled pattern and its options, the
+    /// same logical pattern yields the same fingerprint across runs,
+    /// platforms and enabled features for a given version of this crate.
+    pub fn fingerprint(&self) -> u64 {
+        let mut h = Fnv1a::default();
+        self.canonical_hash(&mut h);
+        h.finish()
+    }
+
+    /// Hashes the options in a fixed order, options that only exist with a
+    /// feature are only hashed when set so they do not change the
+    /// fingerprint of patterns that do not use them
+    #[allow(clippy::too_many_lines)]
+    fn canonical_hash(&self, h: &mut Fnv1a) {
+        let Self {
+            field_seperators,
+            key_seperators,
+            quotes,
+            max_distinct_keys,
+            coerce_values,
+            collapse_consecutive_separators,
+            positional_keys,
+            duplicates,
+            value_array_separator,
+            single_value_arrays,
+            value_separator_map,
+            #[cfg(feature = "unicode-normalization")]
+            normalize,
+            split_strategy,
+            expected_types,
+            coercion_fallback,
+            leading_token_key,
+            respect_brackets,
+            whitespace_field_separator,
+            strip_trailing_separator,
+            flag_policy,
+            empty_values,
+            empty_keys,
+            strip_control_chars,
+            strip_control_chars_in_keys,
+            nested_values,
+            escape_char,
+            inner,
+            trim_first_field,
+            nesting_separator,
+            array_index_notation,
+            index_gaps,
+            dedupe_identical,
+            whole_as_value_key,
+            max_total_depth,
+            recursive,
+            keep_quotes,
+            strip_brackets,
+            max_recursion_depth,
+            include_keys,
+            exclude_keys,
+            trim_key,
+            trim_value,
+            key_prefix,
+            split_once,
+            strict_logfmt,
+            strict,
+            percent_decode,
+            invalid_utf8,
+            record_separator,
+            max_pairs,
+            max_input_length,
+            max_fields,
+            max_key_length,
+            max_value_length,
+            #[cfg(feature = "chrono")]
+            coerce_timestamps,
+            #[cfg(feature = "regex")]
+            field_regex,
+            #[cfg(feature = "regex")]
+            key_regex,
+        } = self;
+        FINGERPRINT_VERSION.hash(h);
+        field_seperators.hash(h);
+        key_seperators.hash(h);
+        quotes.hash(h);
+        max_distinct_keys.hash(h);
+        coerce_values.hash(h);
+        collapse_consecutive_separators.hash(h);
+        positional_keys.hash(h);
+        duplicates.hash(h);
+        value_array_separator.hash(h);
+        single_value_arrays.hash(h);
+        value_separator_map.hash(h);
+        split_strategy.hash(h);
+        expected_types.hash(h);
+        coercion_fallback.hash(h);
+        leading_token_key.hash(h);
+        respect_brackets.hash(h);
+        whitespace_field_separator.hash(h);
+        strip_trailing_separator.hash(h);
+        flag_policy.hash(h);
+        empty_values.hash(h);
+        empty_keys.hash(h);
+        strip_control_chars.hash(h);
+        strip_control_chars_in_keys.hash(h);
+        nested_values.hash(h);
+        escape_char.hash(h);
+        inner.as_ref().map(|p| p.fingerprint()).hash(h);
+        trim_first_field.hash(h);
+        nesting_separator.hash(h);
+        array_index_notation.hash(h);
+        index_gaps.hash(h);
+        dedupe_identical.hash(h);
+        whole_as_value_key.hash(h);
+        max_total_depth.hash(h);
+        recursive.hash(h);
+        keep_quotes.hash(h);
+        strip_brackets.hash(h);
+        max_recursion_depth.hash(h);
+        include_keys.hash(h);
+        exclude_keys.hash(h);
+        trim_key.hash(h);
+        trim_value.hash(h);
+        key_prefix.hash(h);
+        split_once.hash(h);
+        strict_logfmt.hash(h);
+        strict.hash(h);
+        percent_decode.hash(h);
+        invalid_utf8.hash(h);
+        record_separator.hash(h);
+        max_pairs.hash(h);
+        max_input_length.hash(h);
+        max_fields.hash(h);
+        max_key_length.hash(h);
+        max_value_length.hash(h);
+        #[cfg(feature = "unicode-normalization")]
+        if let Some(form) = normalize {
+            "normalize".hash(h);
+            form.hash(h);
+        }
+        #[cfg(feature = "chrono")]
+        if *coerce_timestamps {
+            "coerce_timestamps".hash(h);
+        }
+        #[cfg(feature = "regex")]
+        if let Some(re) = field_regex {
+            "field_regex".hash(h);
+            re.hash(h);
+        }
+        #[cfg(feature = "regex")]
+        if let Some(re) = key_regex {
+            "key_regex".hash(h);
+            re.hash(h);
+        }
+    }
+
+    /// Splits a string that represents KV pairs.
+    ///
+    /// * `input` - The input string
+    ///
+    /// Note: Fields that have on value are dropped.
+    pub fn run<'input, V>(&self, input: &'input str) -> Option<V>
+    where
+        V: Output<'input>,
+    {
+        match self.direct_seperators() {
+            Some((fs, ks)) => self.run_direct(input, fs, ks),
+            None => self.build(self.extract(input)),
+        }
+    }
+
+    /// Splits the input with a single field and key seperator and inserts
+    /// the pairs into the output right away, this gives the same output as
+    /// building it from the extracted pairs for patterns that are split
+    /// with `direct_seperators`.
+    fn run_direct<'input, V>(&self, input: &'input str, fs: &str, ks: &str) -> Option<V>
+    where
+        V: Output<'input>,
+    {
+        if input.trim().is_empty() {
+            return None;
+        }
+        let mut r = V::object();
+        let mut empty = true;
+        for field in self.trim_first(input).split(fs) {
+            let mut parts = field.split(ks);
+            if let (Some(key), Some(val), None) = (parts.next(), parts.next(), parts.next()) {
+                let val = self.scalar(Cow::Borrowed(val), None).ok()??;
+                r.insert(Cow::Borrowed(key), val.into_value::<V>()?).ok()?;
+                empty = false;
+            }
+        }
+        (!empty).then_some(r)
+    }
+
+    /// Splits a string that represents KV pairs like `run` but stops once
+    /// `n` pairs were found, or the maximum number of pairs if it is lower.
+    pub fn run_limited<'input, V>(&self, input: &'input str, n: usize) -> Option<V>
+    where
+        V: Output<'input>,
+    {
+        let budget = Budget {
+            pairs: Some(self.max_pairs.map_or(n, |max| max.min(n))),
+            deadline: None,
+        };
+        self.build(self.extract_within(input, budget, |_, _| ()))
+    }
+
+    /// Splits a string that represents KV pairs like `run_checked` but
+    /// gives up once `deadline` has passed, so pathological inputs can not
+    /// stall the caller. The time is checked every few separators while
+    /// the input is split and every few fields while they are split into
+    /// pairs.
+    ///
+    /// # Errors
+    /// `Error::Timeout` if the deadline passed before the input was split,
+    /// otherwise like `run_checked`
+    pub fn run_with_deadline<'input, V>(
+        &self,
+        input: &'input str,
+        deadline: Instant,
+    ) -> Result<Option<V>, Error>
+    where
+        V: Output<'input>,
+    {
+        let budget = Budget {
+            pairs: self.max_pairs,
+            deadline: Some(deadline),
+        };
+        match self.obj(self.extract_checked(input, budget)?, Depth::top(self)) {
+            Ok(obj) => Ok(obj.and_then(Obj::into_value)),
+            Err(InvalidValue::Depth) => Err(Error::MaxDepthExceeded(self.depth_limit())),
+            Err(InvalidValue::Type) => Ok(None),
+        }
+    }
+
+    /// Splits a string that represents KV pairs like `run` into a value
+    /// that does not borrow the input.
+    #[cfg(feature = "simd-json")]
+    pub fn run_owned(&self, input: &str) -> Option<OwnedValue> {
+        self.run(input)
+    }
+
+    /// Splits a string that represents KV pairs into a map of keys to their
+    /// last value as they appear in the input, like `pairs` this borrows
+    /// the pattern as well as the input. This does not need simd-json.
+    pub fn run_map<'input>(
+        &'input self,
+        input: &'input str,
+    ) -> Option<HashMap<&'input str, &'input str>> {
+        let map: HashMap<_, _> = self.pairs(input).collect();
+        (!map.is_empty()).then_some(map)
+    }
+
+    /// Splits a string that represents KV pairs into a map of keys to their
+    /// last value, values are not coerced or split into arrays.
+    pub fn run_string_map(&self, input: &str) -> Option<HashMap<String, String>> {
+        let map: HashMap<_, _> = self
+            .extract(input)
+            .into_iter()
+            .map(|p| (p.key.into_owned(), p.val.into_owned()))
+            .collect();
+        (!map.is_empty()).then_some(map)
+    }
+
+    /// Splits a string that represents KV pairs like `run` but only keeps
+    /// the pairs for which `pred` returns true.
+    ///
+    /// `pred` is called with the key and the raw value before coercion.
+    pub fn run_filter<'input, V, F>(&self, input: &'input str, pred: F) -> Option<V>
+    where
+        V: Output<'input>,
+        F: Fn(&str, &str) -> bool,
+    {
+        let mut pairs = self.extract(input);
+        pairs.retain(|p| pred(&p.key, &p.val));
+        self.build(pairs)
+    }
+
+    /// Splits a string that represents KV pairs like `run` but only keeps
+    /// the pairs whose key starts with `prefix`, for `strip` the prefix is
+    /// removed from the keys.
+    pub fn run_prefixed<'input, V>(
+        &self,
+        input: &'input str,
+        prefix: &str,
+        strip: bool,
+    ) -> Option<V>
+    where
+        V: Output<'input>,
+    {
+        let pairs = self
+            .extract(input)
+            .into_iter()
+            .filter(|p| p.key.starts_with(prefix))
+            .map(|mut p| {
+                if strip {
+                    p.key = sub_cow(p.key, |k| &k[prefix.len()..]);
+                }
+                p
+            })
+            .collect();
+        self.build(pairs)
+    }
+
+    /// Splits a string that represents KV pairs like `run` but fails if
+    /// values are nested deeper than `with_max_total_depth` allows or the
+    /// input exceeds a configured limit.
+    ///
+    /// # Errors
+    /// `Error::MaxDepthExceeded` if values are nested too deep,
+    /// `Error::LimitExceeded` with the limit the input exceeds
+    pub fn run_checked<'input, V>(&self, input: &'input str) -> Result<Option<V>, Error>
+    where
+        V: Output<'input>,
+    {
+        match self.obj(
+            self.extract_checked(input, self.budget())?,
+            Depth::top(self),
+        ) {
+            Ok(obj) => Ok(obj.and_then(Obj::into_value)),
+            Err(InvalidValue::Depth) => Err(Error::MaxDepthExceeded(self.depth_limit())),
+            Err(InvalidValue::Type) => Ok(None),
+        }
+    }
+
+    /// Splits a string that represents KV pairs like `run` but inserts the
+    /// pairs into an existing object, keys that are already in it are
+    /// replaced. Returns the number of keys inserted, 0 if `run` would
+    /// return `None`.
+    ///
+    /// # Errors
+    /// if `target` is no object or values are nested deeper than the
+    /// maximum total depth or the input exceeds a limit
+    pub fn run_into<'input, V>(&self, input: &'input str, target: &mut V) -> Result<usize, Error>
+    where
+        V: Output<'input>,
+    {
+        match self.obj(
+            self.extract_checked(input, self.budget())?,
+            Depth::top(self),
+        ) {
+            Ok(Some(obj)) => obj.insert_into(target).ok_or(Error::NotAnObject),
+            Ok(None) | Err(InvalidValue::Type) => Ok(0),
+            Err(InvalidValue::Depth) => Err(Error::MaxDepthExceeded(self.depth_limit())),
+        }
+    }
+
+    /// Builds the output from the extracted pairs
+    fn build<'input, V>(&self, pairs: Vec<Pair<'input>>) -> Option<V>
+    where
+        V: Output<'input>,
+    {
+        self.obj(pairs, Depth::top(self)).ok()??.into_value()
+    }
+
+    /// The number of levels values can be nested below the top level
+    fn depth_limit(&self) -> usize {
+        self.max_total_depth.unwrap_or(usize::MAX)
+    }
+
+    /// Collects the extracted pairs in an object, `None` if there are none
+    fn obj<'input>(
+        &self,
+        pairs: Vec<Pair<'input>>,
+        depth: Depth,
+    ) -> Result<Option<Obj<'input>>, InvalidValue> {
+        if pairs.is_empty() {
+            return Ok(None);
+        }
+        let mut r = Obj::with_capacity(pairs.len());
+        let mut seen = HashSet::new();
+        let mut flags: Option<(Cow<str>, Vec<Val>)> = None;
+        for Pair {
+            key,
+            val,
+            nested,
+            flag,
+        } in pairs
+        {
+            if self.dedupe_identical && !seen.insert((key.clone(), val.clone())) {
+                continue;
+            }
+            let val = match &self.flag_policy {
+                FlagPolicy::True if flag => Val::Bool(true),
+                FlagPolicy::Collect(_) if flag => {
+                    flags
+                        .get_or_insert_with(|| (key, Vec::new()))
+                        .1
+                        .push(Val::Str(val));
+                    continue;
+                }
+                _ => match self.value(&key, val, nested, depth)? {
+                    Some(val) => val,
+                    None => continue,
+                },
+            };
+            if let Some(sep) = &self.nesting_separator {
+                let path = split_cow(key, sep);
+                r.insert_path(&path, val, self.duplicates, self.array_index_notation);
+            } else {
+                r.insert(key, val, self.duplicates);
+            }
+        }
+        if let Some((key, vals)) = flags {
+            r.insert(key, Val::Array(vals), DuplicatePolicy::Overwrite);
+        }
+        if self.index_gaps == IndexGapPolicy::Error && r.has_gaps() {
+            return Err(InvalidValue::Type);
+        }
+        Ok(Some(r))
+    }
+
+    /// Reads the input line by line and splits every line into an owned
+    /// value, a final line without a line terminator is parsed as well.
+    ///
+    /// I/O errors (including lines that are not valid UTF-8) are returned
+    /// for the line they occur on.
+    #[cfg(feature = "simd-json")]
+    pub fn run_reader<'p, R: BufRead + 'p>(
+        &'p self,
+        reader: R,
+    ) -> impl Iterator<Item = io::Result<Option<OwnedValue>>> + 'p {
+        reader
+            .lines()
+            .map(move |line| line.map(|line| self.run::<OwnedValue>(&line)))
+    }
+
+    /// Extracts the pairs that make it into the output in input order,
+    /// fields that do not form a pair are dropped.
+    fn extract<'input>(&self, input: &'input str) -> Vec<Pair<'input>> {
+        self.extract_with(input, |_, _| ())
+    }
+
+    /// Extracts the pairs or fails with the limit the input exceeds, because
+    /// the deadline passed, at a rejected empty key or the first field that
+    /// is not split in strict mode
+    fn extract_checked<'input>(
+        &self,
+        input: &'input str,
+        budget: Budget,
+    ) -> Result<Vec<Pair<'input>>, Error> {
+        let mut error = None;
+        let pairs = self.extract_within(input, budget, |reason, field| {
+            let offset = field.as_ptr() as usize - input.as_ptr() as usize;
+            if error.is_none() {
+                error = match reason {
+                    DropReason::Timeout => Some(Error::Timeout),
+                    DropReason::EmptyKey if self.empty_keys == EmptyKeyPolicy::Reject => {
+                        Some(Error::EmptyKey(offset))
+                    }
+                    DropReason::NoKeySeperator | DropReason::TooManyKeySeperators
+                        if self.strict =>
+                    {
+                        Some(Error::UnsplitField(field.to_string(), offset))
+                    }
+                    reason => self.limit(reason).map(Error::LimitExceeded),
+                };
+            }
+        });
+        error.map_or(Ok(pairs), Err)
+    }
+
+    /// The budget for splitting an input when no deadline is given
+    fn budget(&self) -> Budget {
+        Budget {
+            pairs: self.max_pairs,
+            deadline: None,
+        }
+    }
+
+    /// The limit that drops fields for `reason`, if any
+    fn limit(&self, reason: DropReason) -> Option<Limit> {
+        match reason {
+            DropReason::InputTooLong => self.max_input_length.map(Limit::InputLength),
+            DropReason::TooManyFields => self.max_fields.map(Limit::Fields),
+            DropReason::KeyTooLong => self.max_key_length.map(Limit::KeyLength),
+            DropReason::ValueTooLong => self.max_value_length.map(Limit::ValueLength),
+            _ => None,
+        }
+    }
+
+    /// Turns a raw value into the value that gets inserted for `key`, `None`
+    /// if it is dropped
+    fn value<'input>(
+        &self,
+        key: &str,
+        val: Cow<'input, str>,
+        nested: bool,
+        depth: Depth,
+    ) -> Result<Option<Val<'input>>, InvalidValue> {
+        if val.is_empty() && self.empty_values == EmptyValuePolicy::Null {
+            return Ok(Some(Val::Null));
+        }
+        if nested {
+            if let Some(obj) = self.nested(&val) {
+                return if depth.left == 0 {
+                    Err(InvalidValue::Depth)
+                } else {
+                    Ok(Some(obj))
+                };
+            }
+        }
+        if let Some(inner) = &self.inner {
+            let obj = match &val {
+                Cow::Borrowed(v) => inner.sub_obj(v, depth, false)?,
+                Cow::Owned(v) => inner.sub_obj(v, depth, false)?.map(Obj::into_owned),
+            };
+            if let Some(obj) = obj {
+                return Ok(Some(Val::Object(obj)));
+            }
+        }
+        if self.recursive && depth.recursions < self.max_recursion_depth && self.matches(&val) {
+            let obj = match &val {
+                Cow::Borrowed(v) => self.sub_obj(v, depth, true)?,
+                Cow::Owned(v) => self.sub_obj(v, depth, true)?.map(Obj::into_owned),
+            };
+            if let Some(obj) = obj {
+                return Ok(Some(Val::Object(obj)));
+            }
+        }
+        let mut val = val;
+        for (from, to) in &self.value_separator_map {
+            if val.contains(from.as_str()) {
+                val = Cow::Owned(val.replace(from.as_str(), to));
+            }
+        }
+        let expected = self.expected_types.get(key).copied();
+        if let Some(sep) = &self.value_array_separator {
+            if self.single_value_arrays || val.contains(sep.as_str()) {
+                let mut vals = Vec::new();
+                for v in split_cow(val, sep) {
+                    vals.extend(self.scalar(v, expected)?);
+                }
+                return Ok(Some(Val::Array(vals)));
+            }
+        }
+        self.scalar(val, expected)
+    }
+
+    /// Splits a value into an object one level below `depth`, `None` if
+    /// there are no pairs in it. `recursion` is set if this pattern split
+    /// the value.
+    fn sub_obj<'v>(
+        &self,
+        val: &'v str,
+        depth: Depth,
+        recursion: bool,
+    ) -> Result<Option<Obj<'v>>, InvalidValue> {
+        let pairs = self.extract(val);
+        if pairs.is_empty() {
+            return Ok(None);
+        }
+        self.obj(pairs, depth.below(self, recursion)?)
+    }
+
+    /// Splits a nested value like `type:error` into a single entry object
+    fn nested<'input>(&self, val: &Cow<'input, str>) -> Option<Val<'input>> {
+        let splitter = Splitter::new(self, val);
+        let [sub, inner] = splitter.split_key(val)[..] else {
+            return None;
+        };
+        let sub = splitter.offset(sub)..splitter.offset(sub) + sub.len();
+        let inner = splitter.offset(inner)..;
+        let sub = sub_cow(val.clone(), |s| unquote(&s[sub.clone()], &self.quotes));
+        let inner = if self.keep_quotes {
+            sub_cow(val.clone(), |s| &s[inner.clone()])
+        } else {
+            sub_cow(val.clone(), |s| unquote(&s[inner.clone()], &self.quotes))
+        };
+        let mut obj = Obj::with_capacity(1);
+        obj.insert(sub, self.scalar(inner, None).ok()??, self.duplicates);
+        Some(Val::Object(obj))
+    }
+
+    /// Turns a raw value into a scalar value, `None` if it is dropped
+    fn scalar<'input>(
+        &self,
+        val: Cow<'input, str>,
+        expected: Option<ExpectedType>,
+    ) -> Result<Option<Val<'input>>, InvalidValue> {
+        if let Some(expected) = expected {
+            if let Some(v) = Val::expect(&val, expected) {
+                return Ok(Some(v));
+            }
+            return match self.coercion_fallback {
+                CoercionFallback::AsString => Ok(Some(Val::Str(val))),
+                CoercionFallback::Null => Ok(Some(Val::Null)),
+                CoercionFallback::Drop => Ok(None),
+                CoercionFallback::Error => Err(InvalidValue::Type),
+            };
+        }
+        #[cfg(feature = "chrono")]
+        if self.coerce_timestamps {
+            if let Some(ms) = timestamp::epoch_millis(&val) {
+                return Ok(Some(Val::I64(ms)));
+            }
+        }
+        Ok(Some(if self.coerce_values {
+            Val::coerce(val)
+        } else {
+            Val::Str(val)
+        }))
+    }
+
+    /// Checks if the input conforms to the pattern, that is every non empty
+    /// field in the input forms a key value pair and there is at least one
+    /// pair.
+    pub fn matches(&self, input: &str) -> bool {
+        if input.trim().is_empty() {
+            return false;
+        }
+        let splitter = Splitter::new(self, input);
+        let mut pairs = false;
+        for field in splitter.fields(input) {
+            if field.is_empty() {
+                continue;
+            }
+            if splitter.split_key(field).len() != 2 {
+                return false;
+            }
+            pairs = true;
+        }
+        pairs
+    }
+
+    /// Returns the input with the seperators it is split at marked, field
+    /// seperators are enclosed in `|` and key seperators in `[]`, e.g.
+    /// `a=1 b=2` becomes `a[=]1| |b[=]2`.
+    ///
+    /// This only shows how the input is tokenized, it is meant as a
+    /// debugging aid.
+    pub fn annotate_input(&self, input: &str) -> String {
+        let splitter = Splitter::new(self, input);
+        let mut r = String::with_capacity(input.len() * 2);
+        let mut end = 0;
+        for field in splitter.fields(input) {
+            let start = splitter.offset(field);
+            if start > end {
+                r.push('|');
+                r.push_str(&input[end..start]);
+                r.push('|');
+            }
+            let mut part_end = start;
+            for part in splitter.split_key(field) {
+                let part_start = splitter.offset(part);
+                if part_start > part_end {
+                    r.push('[');
+                    r.push_str(&input[part_end..part_start]);
+                    r.push(']');
+                }
+                r.push_str(part);
+                part_end = part_start + part.len();
+            }
+            end = start + field.len();
+        }
+        if end < input.len() {
+            r.push('|');
+            r.push_str(&input[end..]);
+            r.push('|');
+        }
+        r
+    }
+
+    /// Extracts the pairs that make it into the output in input order,
+    /// `dropped` is called with the reason and the text of every field
+    /// that is dropped.
+    fn extract_with<'input, F>(&self, input: &'input str, dropped: F) -> Vec<Pair<'input>>
+    where
+        F: FnMut(DropReason, &'input str),
+    {
+        self.extract_within(input, self.budget(), dropped)
+    }
+
+    /// Extracts pairs within the budget
+    fn extract_within<'input, F>(
+        &self,
+        input: &'input str,
+        budget: Budget,
+        mut dropped: F,
+    ) -> Vec<Pair<'input>>
+    where
+        F: FnMut(DropReason, &'input str),
+    {
+        let mut unsplit = false;
+        let raw = self.raw_pairs_with(input, budget, &mut |reason, field| {
+            unsplit |= matches!(
+                reason,
+                DropReason::NoKeySeperator | DropReason::TooManyKeySeperators
+            );
+            dropped(reason, field);
+        });
+        if unsplit && self.strict {
+            return Vec::new();
+        }
+        let mut pairs = Vec::new();
+        let mut keys = HashSet::new();
+        for RawPair {
+            field,
+            key,
+            val,
+            flag,
+            nested,
+        } in raw
+        {
+            let (key, val) = match &self.flag_policy {
+                FlagPolicy::Constant(c) if flag => (key, Cow::Owned(c.clone())),
+                FlagPolicy::True if flag => (key, Cow::Borrowed("true")),
+                FlagPolicy::Collect(k) if flag => (Cow::Owned(k.clone()), key),
+                _ => (key, Cow::Borrowed(val)),
+            };
+            #[cfg(feature = "unicode-normalization")]
+            let (key, val) = match self.normalize {
+                Some(form) => (form.normalize(key), form.normalize(val)),
+                None => (key, val),
+            };
+            let (key, val) = match self.escape_char {
+                Some(escape) => (unescape(key, escape), unescape(val, escape)),
+                None => (key, val),
+            };
+            let (key, val) = if self.percent_decode {
+                (query::percent_decode(key), query::percent_decode(val))
+            } else {
+                (key, val)
+            };
+            let key = if self.strip_control_chars_in_keys {
+                strip_control_chars(key)
+            } else {
+                key
+            };
+            let key = match &self.trim_key {
+                Some(trim) => sub_cow(key, |k| trim.trim(k)),
+                None => key,
+            };
+            let key = match &self.empty_keys {
+                _ if !key.is_empty() => key,
+                EmptyKeyPolicy::Keep => key,
+                EmptyKeyPolicy::Rename(k) => Cow::Owned(k.clone()),
+                EmptyKeyPolicy::Drop => {
+                    dropped(DropReason::EmptyKey, field);
+                    continue;
+                }
+                EmptyKeyPolicy::Reject => {
+                    dropped(DropReason::EmptyKey, field);
+                    return Vec::new();
+                }
+            };
+            if !self.keeps(&key) {
+                continue;
+            }
+            let key = match &self.key_prefix {
+                Some(prefix) => Cow::Owned(format!("{prefix}{key}")),
+                None => key,
+            };
+            let val = if self.strip_control_chars {
+                strip_control_chars(val)
+            } else {
+                val
+            };
+            let val = match &self.trim_value {
+                Some(trim) => sub_cow(val, |v| trim.trim(v)),
+                None => val,
+            };
+            if val.is_empty() && self.empty_values == EmptyValuePolicy::Drop {
+                continue;
+            }
+            if let Some(max) = self.max_distinct_keys {
+                if !keys.contains(&key) {
+                    if keys.len() >= max {
+                        dropped(DropReason::DistinctKeyLimit, field);
+                        continue;
+                    }
+                    keys.insert(key.clone());
+                }
+            }
+            pairs.push(Pair {
+                key,
+                val,
+                nested,
+                flag,
+            });
+        }
+        pairs
+    }
+
+    /// If pairs with `key` are kept by the include and exclude keys
+    fn keeps(&self, key: &str) -> bool {
+        (self.include_keys.is_empty() || self.include_keys.contains(key))
+            && !self.exclude_keys.contains(key)
+    }
+
+    /// Splits the input into the pairs as they appear in the input, fields
+    /// that do not form a pair are dropped.
+    ///
+    /// Leading fields without a key seperator are stored under the
+    /// positional keys if there are any. Input that is empty or consists of
+    /// whitespace only never produces pairs.
+    ///
+    /// With a leading token key the input up to the first run of whitespace
+    /// is stored under it before the rest is split. Splitting stops once
+    /// the budget's number of pairs is reached, inputs that exceed a
+    /// configured limit or the deadline have no pairs at all.
+    fn raw_pairs_with<'input, F>(
+        &self,
+        input: &'input str,
+        budget: Budget,
+        dropped: &mut F,
+    ) -> Vec<RawPair<'input>>
+    where
+        F: FnMut(DropReason, &'input str),
+    {
+        if input.trim().is_empty() {
+            return Vec::new();
+        }
+        if self.max_input_length.is_some_and(|max| input.len() > max) {
+            dropped(DropReason::InputTooLong, input);
+            return Vec::new();
+        }
+        let (mut pairs, input) = self.leading_token(self.trim_first(input));
+        let splitter = Splitter::new(self, input).until(budget.deadline);
+        let mut positional = Some(self.positional_keys.iter());
+        let fields = splitter.fields(input);
+        if let Some(&field) = self.max_fields.and_then(|max| fields.get(max)) {
+            dropped(DropReason::TooManyFields, field);
+            return Vec::new();
+        }
+        for (i, field) in fields.into_iter().enumerate() {
+            if budget.pairs.is_some_and(|max| pairs.len() >= max) {
+                break;
+            }
+            if let Some(reason) = self.rejected(field, splitter.deadline.passed(i)) {
+                dropped(reason, field);
+                return Vec::new();
+            }
+            let mut flag = false;
+            let mut nested = false;
+            let (key, val) = match splitter.split_key(field)[..] {
+                [key, val] => {
+                    positional = None;
+                    (Cow::Borrowed(unquote(key, &self.quotes)), val)
+                }
+                [] | [""] => continue,
+                [val] => {
+                    if let Some(key) = positional.as_mut().and_then(Iterator::next) {
+                        (Cow::Owned(key.clone()), val)
+                    } else if self.flag_policy != FlagPolicy::Drop {
+                        flag = true;
+                        (Cow::Borrowed(unquote(val, &self.quotes)), val)
+                    } else {
+                        dropped(DropReason::NoKeySeperator, field);
+                        continue;
+                    }
+                }
+                [key, sub, _] if self.nested_values => {
+                    positional = None;
+                    nested = true;
+                    let val = &field[splitter.offset(sub) - splitter.offset(field)..];
+                    (Cow::Borrowed(unquote(key, &self.quotes)), val)
+                }
+                [key, rest, ..] if self.split_once => {
+                    positional = None;
+                    let val = &field[splitter.offset(rest) - splitter.offset(field)..];
+                    (Cow::Borrowed(unquote(key, &self.quotes)), val)
+                }
+                [key, ..] => {
+                    let val = strip_seperators(&field[key.len()..], &self.key_seperators);
+                    if self.strip_trailing_separator && splitter.split_key(val).len() == 1 {
+                        positional = None;
+                        (Cow::Borrowed(unquote(key, &self.quotes)), val)
+                    } else {
+                        dropped(DropReason::TooManyKeySeperators, field);
+                        continue;
+                    }
+                }
+            };
+            let mut val = if nested || self.keep_quotes {
+                val
+            } else {
+                unquote(val, &self.quotes)
+            };
+            if self.respect_brackets && self.strip_brackets && !nested {
+                val = unbracket(val);
+            }
+            if let Some(reason) = self.too_long(&key, val) {
+                dropped(reason, field);
+                return Vec::new();
+            }
+            pairs.push(RawPair {
+                field,
+                key,
+                val,
+                flag,
+                nested,
+            });
+        }
+        // splitting keys might have stopped early as well
+        if splitter.timed_out() {
+            dropped(DropReason::Timeout, input);
+            return Vec::new();
+        }
+        if let Some(key) = self
+            .whole_as_value_key
+            .as_ref()
+            .filter(|_| pairs.is_empty() && budget.pairs != Some(0))
+        {
+            pairs.push(RawPair::configured(key, input.trim()));
+        }
+        pairs
+    }
+
+    /// The pair of the leading token if there is a leading token key and
+    /// the rest of the input
+    fn leading_token<'input>(&self, input: &'input str) -> (Vec<RawPair<'input>>, &'input str) {
+        match &self.leading_token_key {
+            Some(key) => {
+                let rest = input.trim_start();
+                let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                let (token, rest) = rest.split_at(end);
+                (vec![RawPair::configured(key, token)], rest.trim_start())
+            }
+            None => (Vec::new(), input),
+        }
+    }
+
+    /// Why the whole input is rejected at `field`, if it is
+    fn rejected(&self, field: &str, expired: bool) -> Option<DropReason> {
+        if expired {
+            Some(DropReason::Timeout)
+        } else if self.strict_logfmt && !field.is_empty() && !logfmt::well_formed(field) {
+            Some(DropReason::Malformed)
+        } else {
+            None
+        }
+    }
+
+    /// Why a pair is rejected if its key or value exceeds the length limits
+    fn too_long(&self, key: &str, val: &str) -> Option<DropReason> {
+        if self.max_key_length.is_some_and(|max| key.len() > max) {
+            Some(DropReason::KeyTooLong)
+        } else if self.max_value_length.is_some_and(|max| val.len() > max) {
+            Some(DropReason::ValueTooLong)
+        } else {
+            None
+        }
+    }
+
+    /// Splits the input into the pairs as they appear in the input
+    fn raw_pairs<'input>(&self, input: &'input str) -> Vec<RawPair<'input>> {
+        self.raw_pairs_with(input, self.budget(), &mut |_, _| ())
+    }
+
+    /// Returns the `n`th (starting at 0) value of `key` in the input
+    /// regardless of the duplicate policy.
+    ///
+    /// The value is returned as it appears in the input without being
+    /// coerced or normalized.
+    pub fn get_nth<'input>(&self, input: &'input str, key: &str, n: usize) -> Option<&'input str> {
+        self.raw_pairs(input)
+            .into_iter()
+            .filter(|p| p.key == key)
+            .nth(n)
+            .map(|p| p.val)
+    }
+
+    /// Returns the first value of `key` in the input without building an
+    /// object, regardless of the duplicate policy. Scanning stops at the
+    /// first match.
+    ///
+    /// The value is returned as it appears in the input without being
+    /// coerced or normalized. For patterns with a single field and key
+    /// seperator and no options that change how the input is split this
+    /// does not allocate.
+    pub fn find<'input>(&self, input: &'input str, key: &str) -> Option<&'input str> {
+        if let Some((fs, ks)) = self.single_seperators() {
+            if input.trim().is_empty() {
+                return None;
+            }
+            return self.trim_first(input).split(fs).find_map(|field| {
+                let mut parts = field.split(ks);
+                match (parts.next(), parts.next(), parts.next()) {
+                    (Some(k), Some(val), None) if k == key => Some(val),
+                    _ => None,
+                }
+            });
+        }
+        self.raw_pairs(input)
+            .into_iter()
+            .find(|p| p.key == key)
+            .map(|p| p.val)
+    }
+
+    /// If the input has a pair with `key`, see `find`
+    pub fn contains_key(&self, input: &str, key: &str) -> bool {
+        self.find(input, key).is_some()
+    }
+
+    /// Splits the input into parallel arrays of keys and values in input
+    /// order, repeated keys are kept.
+    ///
+    /// Values are returned as they appear in the input without being
+    /// coerced or normalized. The output borrows the pattern as well as the
+    /// input since positional keys come from the pattern.
+    pub fn run_columns<'input>(
+        &'input self,
+        input: &'input str,
+    ) -> (Vec<&'input str>, Vec<&'input str>) {
+        let pairs = self.raw_pairs(input);
+        let mut keys = Vec::with_capacity(pairs.len());
+        let mut vals = Vec::with_capacity(pairs.len());
+        for RawPair { key, val, .. } in pairs {
+            keys.push(self.raw_key(key));
+            vals.push(val);
+        }
+        (keys, vals)
+    }
+
+    /// Splits the input into pairs sorted by value, ties are sorted by key.
+    /// Repeated keys are kept.
+    ///
+    /// Values are compared as strings, with `with_coerce_values` values
+    /// that are numbers are compared numerically and come before all other
+    /// values. Values are returned as they appear in the input without
+    /// being coerced or normalized.
+    pub fn run_sorted_by_value<'input>(
+        &'input self,
+        input: &'input str,
+    ) -> Vec<(&'input str, &'input str)> {
+        let mut pairs: Vec<_> = self
+            .raw_pairs(input)
+            .into_iter()
+            .map(|p| (self.raw_key(p.key), p.val))
+            .collect();
+        if self.coerce_values {
+            pairs.sort_by(|(k1, v1), (k2, v2)| {
+                let (n1, n2) = (
+                    Val::coerce(Cow::Borrowed(v1)),
+                    Val::coerce(Cow::Borrowed(v2)),
+                );
+                let order = n1.number_cmp(&n2).unwrap_or_else(|| {
+                    n2.is_number().cmp(&n1.is_number()).then_with(|| v1.cmp(v2))
+                });
+                order.then_with(|| k1.cmp(k2))
+            });
+        } else {
+            pairs.sort_by(|(k1, v1), (k2, v2)| v1.cmp(v2).then_with(|| k1.cmp(k2)));
+        }
+        pairs
+    }
+
+    /// Turns the key of a raw pair into a slice of the input or the pattern
+    fn raw_key<'input>(&'input self, key: Cow<'input, str>) -> &'input str {
+        match key {
+            Cow::Borrowed(key) => key,
+            Cow::Owned(key) => self
+                .positional_keys
+                .iter()
+                .chain(&self.leading_token_key)
+                .chain(&self.whole_as_value_key)
+                .find(|k| **k == key)
+                .map_or("", String::as_str),
+        }
+    }
+
+    /// Lazily yields the pairs of the input in input order, repeated keys
+    /// are kept. Flags are no pairs so they are skipped regardless of the
+    /// `FlagPolicy`.
+    ///
+    /// Values are returned as they appear in the input without being
+    /// coerced or normalized. For patterns with a single field and key
+    /// seperator and no options that change how the input is split pairs
+    /// are found as the iterator advances without allocating, otherwise
+    /// the input is split up front. The pairs borrow the pattern as well
+    /// as the input since positional keys come from the pattern.
+    pub fn pairs<'input>(
+        &'input self,
+        input: &'input str,
+    ) -> impl Iterator<Item = (&'input str, &'input str)> + 'input {
+        // like splitting up front blank inputs have no pairs
+        let single = self
+            .single_seperators()
+            .filter(|_| !input.trim().is_empty());
+        let lazy = single.map(|(fs, ks)| {
+            self.trim_first(input).split(fs).filter_map(move |field| {
+                let mut parts = field.split(ks);
+                match (parts.next(), parts.next(), parts.next()) {
+                    (Some(key), Some(val), None) => Some((key, val)),
+                    _ => None,
+                }
+            })
+        });
+        let split = single.is_none().then(|| {
+            self.raw_pairs(input)
+                .into_iter()
+                .filter(|p| !p.flag)
+                .map(|p| (self.raw_key(p.key), p.val))
+        });
+        lazy.into_iter()
+            .flatten()
+            .chain(split.into_iter().flatten())
+    }
+
+    /// Fills `out` with the values of the keys in `schema`, `out[i]` is the
+    /// last value of `schema[i]` in the input or `None` if it is missing.
+    ///
+    /// Values are returned as they appear in the input without being
+    /// coerced or normalized. For patterns with a single field and key
+    /// seperator and no options that change how the input is split this
+    /// does not allocate.
+    ///
+    /// # Panics
+    /// if `schema` and `out` differ in length
+    pub fn fill<'input>(
+        &self,
+        input: &'input str,
+        schema: &[&str],
+        out: &mut [Option<&'input str>],
+    ) {
+        assert_eq!(
+            schema.len(),
+            out.len(),
+            "schema and output slice differ in length"
+        );
+        out.fill(None);
+        let mut set = |key: &str, val: &'input str| {
+            if let Some(i) = schema.iter().position(|k| *k == key) {
+                out[i] = Some(val);
+            }
+        };
+        if let Some((fs, ks)) = self.single_seperators() {
+            if input.trim().is_empty() {
+                return;
+            }
+            for field in self.trim_first(input).split(fs) {
+                let mut parts = field.split(ks);
+                if let (Some(key), Some(val), None) = (parts.next(), parts.next(), parts.next()) {
+                    set(key, val);
+                }
+            }
+        } else {
+            for p in self.raw_pairs(input) {
+                set(&p.key, p.val);
+            }
+        }
+    }
+
+    /// Trims the start of the first field if configured to
+    fn trim_first<'input>(&self, input: &'input str) -> &'input str {
+        if self.trim_first_field {
+            input.trim_start_matches(|c: char| c == '\u{feff}' || c.is_whitespace())
+        } else {
+            input
+        }
+    }
+
+    /// The field and key seperator if the input can be split with them
+    /// alone
+    fn single_seperators(&self) -> Option<(&str, &str)> {
+        (self.fast_path() >= FastPath::Split).then(|| self.plain_seperators())
+    }
+
+    /// The field and key seperator if the pairs of the input need neither
+    /// be transformed nor collected in an object before they are inserted
+    /// into the output, beyond coercing values
+    fn direct_seperators(&self) -> Option<(&str, &str)> {
+        (self.fast_path() == FastPath::Direct).then(|| self.plain_seperators())
+    }
+
+    fn plain_seperators(&self) -> (&str, &str) {
+        (&self.field_seperators[0], &self.key_seperators[0])
+    }
+
+    /// Works out which fast path the options allow. Every option is listed
+    /// so a new one does not compile until it is sorted into the options
+    /// that change how the input is split, the ones that change the pairs
+    /// or the ones neither path cares about.
+    #[allow(clippy::too_many_lines)]
+    fn fast_path(&self) -> FastPath {
+        let Self {
+            field_seperators,
+            key_seperators,
+            quotes,
+            max_distinct_keys,
+            coerce_values: _,
+            collapse_consecutive_separators,
+            positional_keys,
+            duplicates,
+            value_array_separator,
+            single_value_arrays: _,
+            value_separator_map,
+            #[cfg(feature = "unicode-normalization")]
+            normalize,
+            split_strategy: _,
+            expected_types,
+            coercion_fallback: _,
+            leading_token_key,
+            respect_brackets,
+            whitespace_field_separator,
+            strip_trailing_separator,
+            flag_policy,
+            empty_values,
+            empty_keys,
+            strip_control_chars,
+            strip_control_chars_in_keys,
+            nested_values,
+            escape_char,
+            inner,
+            trim_first_field: _,
+            nesting_separator,
+            array_index_notation: _,
+            index_gaps: _,
+            dedupe_identical,
+            whole_as_value_key,
+            max_total_depth: _,
+            recursive,
+            keep_quotes: _,
+            strip_brackets: _,
+            max_recursion_depth: _,
+            include_keys,
+            exclude_keys,
+            trim_key,
+            trim_value,
+            key_prefix,
+            split_once,
+            strict_logfmt,
+            strict,
+            percent_decode,
+            invalid_utf8: _,
+            record_separator: _,
+            max_pairs,
+            max_input_length,
+            max_fields,
+            max_key_length,
+            max_value_length,
+            #[cfg(feature = "chrono")]
+            coerce_timestamps,
+            #[cfg(feature = "regex")]
+            field_regex,
+            #[cfg(feature = "regex")]
+            key_regex,
+        } = self;
+        // neither path cares about these, regular expressions leave the
+        // literal seperators empty
+        #[cfg(feature = "chrono")]
+        let _ = coerce_timestamps;
+        #[cfg(feature = "regex")]
+        let _ = (field_regex, key_regex);
+        let split = field_seperators.len() == 1
+            && key_seperators.len() == 1
+            && quotes.is_empty()
+            && !respect_brackets
+            && !whitespace_field_separator
+            && !strip_trailing_separator
+            && *flag_policy == FlagPolicy::Drop
+            && !nested_values
+            && !split_once
+            && !strict_logfmt
+            && escape_char.is_none()
+            && !collapse_consecutive_separators
+            && positional_keys.is_empty()
+            && leading_token_key.is_none()
+            && whole_as_value_key.is_none()
+            && max_pairs.is_none()
+            && max_input_length.is_none()
+            && max_fields.is_none()
+            && max_key_length.is_none()
+            && max_value_length.is_none();
+        #[cfg(feature = "unicode-normalization")]
+        let normalized = normalize.is_some();
+        #[cfg(not(feature = "unicode-normalization"))]
+        let normalized = false;
+        let direct = !normalized
+            && *duplicates == DuplicatePolicy::Overwrite
+            && !dedupe_identical
+            && *empty_values == EmptyValuePolicy::Keep
+            && *empty_keys == EmptyKeyPolicy::Keep
+            && !strict
+            && !percent_decode
+            && !strip_control_chars
+            && !strip_control_chars_in_keys
+            && trim_key.is_none()
+            && trim_value.is_none()
+            && include_keys.is_empty()
+            && exclude_keys.is_empty()
+            && key_prefix.is_none()
+            && max_distinct_keys.is_none()
+            && nesting_separator.is_none()
+            && inner.is_none()
+            && !recursive
+            && value_separator_map.is_empty()
+            && value_array_separator.is_none()
+            && expected_types.is_empty();
+        match (split, direct) {
+            (false, _) => FastPath::None,
+            (true, false) => FastPath::Split,
+            (true, true) => FastPath::Direct,
+        }
+    }
+}
+
+/// How much of the general pipeline an input can skip
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum FastPath {
+    /// the input has to be split in full
+    None,
+    /// the input can be split on the single field and key seperator
+    Split,
+    /// the pairs can also go into the output as they are split
+    Direct,
+}
+
+/// How much deeper values can be nested while building an object
+#[derive(Debug, Clone, Copy)]
+struct Depth {
+    /// the number of levels left below the current one
+    left: usize,
+    /// the number of times the current pattern recursed
+    recursions: usize,
+}
+
+impl Depth {
+    /// The depth of the top level object built by `pattern`
+    fn top(pattern: &Pattern) -> Self {
+        Self {
+            left: pattern.depth_limit(),
+            recursions: 0,
+        }
+    }
+
+    /// The depth one level below for values split by `pattern`, `recursion`
+    /// is set if `pattern` is the pattern of the current level
+    fn below(self, pattern: &Pattern, recursion: bool) -> Result<Self, InvalidValue> {
+        Ok(Self {
+            left: self
+                .left
+                .checked_sub(1)
+                .ok_or(InvalidValue::Depth)?
+                .min(pattern.depth_limit()),
+            recursions: if recursion { self.recursions + 1 } else { 0 },
+        })
+    }
+}
+
+/// A pair as it appears in the input
+struct RawPair<'input> {
+    /// the field the pair was split from
+    field: &'input str,
+    key: Cow<'input, str>,
+    val: &'input str,
+    /// the field has no key seperator and is kept as a flag, its value is
+    /// the field itself
+    flag: bool,
+    /// the value is a nested key value pair
+    nested: bool,
+}
+
+impl<'input> RawPair<'input> {
+    /// A pair with a configured key for a part of the input
+    fn configured(key: &str, val: &'input str) -> Self {
+        Self {
+            field: val,
+            key: Cow::Owned(key.to_string()),
+            val,
+            flag: false,
+            nested: false,
+        }
+    }
+}
+
+/// How much splitting an input may take
+#[derive(Debug, Clone, Copy)]
+struct Budget {
+    /// the number of pairs after which splitting stops
+    pairs: Option<usize>,
+    /// the time after which splitting fails
+    deadline: Option<Instant>,
+}
+
+/// The deadline while splitting the input, once it passed it stays passed
+#[derive(Debug, Default)]
+struct Deadline {
+    at: Option<Instant>,
+    passed: Cell<bool>,
+}
+
+impl Deadline {
+    /// The number of parts split between checks of the deadline
+    const CHECK_INTERVAL: usize = 64;
+
+    /// If the deadline passed, this is only checked every few parts
+    fn passed(&self, parts: usize) -> bool {
+        if !self.passed.get()
+            && self
+                .at
+                .is_some_and(|d| parts.is_multiple_of(Self::CHECK_INTERVAL) && Instant::now() >= d)
+        {
+            self.passed.set(true);
+        }
+        self.passed.get()
+    }
+}
+
+/// A FNV-1a hasher, unlike the std hashers it has a fixed seed and hashes
+/// integers in little endian so hashes are stable.
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= u64::from(*b);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes());
+    }
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+}
+
+/// A key value pair
+#[derive(Debug, Clone)]
+struct Pair<'input> {
+    key: Cow<'input, str>,
+    val: Cow<'input, str>,
+    /// the value is a nested key value pair, e.g. `type:error` in
+    /// `meta=type:error`
+    nested: bool,
+    /// the pair comes from a field without a key seperator
+    flag: bool,
+}
+
+/// Splits one input into fields and fields into keys and values
+struct Splitter<'p> {
+    pattern: &'p Pattern,
+    /// start of the input, used to turn sub slices into offsets
+    start: usize,
+    /// quoted, escaped and bracketed spans seperators are ignored in
+    quoted: Vec<(usize, usize)>,
+    /// splitting stops early once it passed
+    deadline: Deadline,
+}
+
+/// Seperators together with how they are matched, this is worked out once
+/// when the pattern is built. They compare, hash and serialize as the list
+/// of seperators.
+#[derive(Debug, Clone)]
+struct Seperators {
+    list: Vec<String>,
+    /// the first bytes of the seperators
+    first: [bool; 256],
+    /// see `overlapping`
+    overlapping: bool,
+}
+
+impl From<Vec<String>> for Seperators {
+    fn from(list: Vec<String>) -> Self {
+        let mut first = [false; 256];
+        for b in list.iter().filter_map(|s| s.as_bytes().first()) {
+            first[usize::from(*b)] = true;
+        }
+        Self {
+            overlapping: overlapping(&list),
+            list,
+            first,
+        }
+    }
+}
+
+impl Default for Seperators {
+    fn default() -> Self {
+        Self::from(Vec::new())
+    }
+}
+
+impl std::ops::Deref for Seperators {
+    type Target = [String];
+    fn deref(&self) -> &[String] {
+        &self.list
+    }
+}
+
+impl PartialEq for Seperators {
+    fn eq(&self, other: &Self) -> bool {
+        self.list == other.list
+    }
+}
+
+impl Eq for Seperators {}
+
+impl Hash for Seperators {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.list.hash(state);
+    }
+}
+
+impl Serialize for Seperators {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.list.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Seperators {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::deserialize(deserializer).map(Self::from)
+    }
+}
+
+impl<'p> Splitter<'p> {
+    fn new(pattern: &'p Pattern, input: &str) -> Self {
+        let mut quoted = quoted_spans(input, &pattern.quotes);
+        if let Some(escape) = pattern.escape_char {
+            quoted.extend(escaped_spans(input, escape));
+        }
+        if pattern.respect_brackets {
+            quoted = merge_spans(quoted);
+            let brackets = bracket_spans(input, &quoted);
+            quoted.extend(brackets);
+        }
+        Self {
+            pattern,
+            start: input.as_ptr() as usize,
+            quoted: merge_spans(quoted),
+            deadline: Deadline::default(),
+        }
+    }
+
+    /// Stops splitting early once `deadline` passed
+    fn until(mut self, deadline: Option<Instant>) -> Self {
+        self.deadline.at = deadline;
+        self
+    }
+
+    /// If splitting stopped early because the deadline passed
+    fn timed_out(&self) -> bool {
+        self.deadline.passed.get()
+    }
+
+    /// The offset of a sub slice of the input
+    fn offset(&self, s: &str) -> usize {
+        s.as_ptr() as usize - self.start
+    }
+
+    /// If the offset is inside a quoted span, spans are sorted and do not
+    /// overlap so this is a binary search
+    fn is_quoted(&self, i: usize) -> bool {
+        let n = self.quoted.partition_point(|&(s, _)| s < i);
+        n > 0 && i < self.quoted[n - 1].1
+    }
+
+    /// Splits a sub slice of the input on the seperators, seperators that
+    /// start inside a quoted span are ignored
+    fn split<'input>(&self, input: &'input str, seperators: &Seperators) -> Vec<&'input str> {
+        use std::mem;
+        if self.pattern.split_strategy == SplitStrategy::LongestMatch {
+            return self.split_longest(input, &seperators.list);
+        }
+        if self.quoted.is_empty() && !self.pattern.collapse_consecutive_separators {
+            return multi_split(input, seperators, &self.deadline);
+        }
+        let mut i: Vec<&str> = vec![input];
+        let mut i1 = vec![];
+        for s in &seperators.list {
+            i1.clear();
+            for e in &i {
+                let o = self.offset(e);
+                let mut start = 0;
+                for (idx, _) in e.match_indices(s.as_str()) {
+                    if self.is_quoted(o + idx) {
+                        continue;
+                    }
+                    if self.deadline.passed(i1.len()) {
+                        return i1;
+                    }
+                    // with collapsing a run of the same seperator only
+                    // splits once
+                    if !(self.pattern.collapse_consecutive_separators && start == idx && start > 0)
+                    {
+                        i1.push(&e[start..idx]);
+                    }
+                    start = idx + s.len();
+                }
+                i1.push(&e[start..]);
+            }
+            mem::swap(&mut i, &mut i1);
+        }
+        i
+    }
+
+    /// Splits the input into fields
+    fn fields<'input>(&self, input: &'input str) -> Vec<&'input str> {
+        #[cfg(feature = "regex")]
+        let fields = match &self.pattern.field_regex {
+            Some(re) => self.split_regex(input, &re.0),
+            None => self.split(input, &self.pattern.field_seperators),
+        };
+        #[cfg(not(feature = "regex"))]
+        let fields = self.split(input, &self.pattern.field_seperators);
+        if !self.pattern.whitespace_field_separator {
+            return fields;
+        }
+        let mut r = Vec::with_capacity(fields.len());
+        for field in fields {
+            let o = self.offset(field);
+            let mut start = None;
+            for (i, c) in field.char_indices() {
+                if c.is_whitespace() && !self.is_quoted(o + i) {
+                    if self.deadline.passed(r.len()) {
+                        return r;
+                    }
+                    if let Some(s) = start.take() {
+                        r.push(&field[s..i]);
+                    }
+                } else if start.is_none() {
+                    start = Some(i);
+                }
+            }
+            if let Some(s) = start {
+                r.push(&field[s..]);
+            }
+        }
+        r
+    }
+
+    /// Splits a field into key and value
+    fn split_key<'input>(&self, field: &'input str) -> Vec<&'input str> {
+        #[cfg(feature = "regex")]
+        if let Some(re) = &self.pattern.key_regex {
+            return self.split_regex(field, &re.0);
+        }
+        self.split(field, &self.pattern.key_seperators)
+    }
+
+    /// Splits at the matches of a regular expression, empty matches and
+    /// matches that start inside a quoted span are ignored
+    #[cfg(feature = "regex")]
+    fn split_regex<'input>(&self, input: &'input str, re: &regex::Regex) -> Vec<&'input str> {
+        let collapse = self.pattern.collapse_consecutive_separators;
+        let o = self.offset(input);
+        let mut parts = Vec::new();
+        let mut start = 0;
+        for m in re.find_iter(input) {
+            if m.is_empty() || self.is_quoted(o + m.start()) {
+                continue;
+            }
+            if self.deadline.passed(parts.len()) {
+                return parts;
+            }
+            if !(collapse && start == m.start() && start > 0) {
+                parts.push(&input[start..m.start()]);
+            }
+            start = m.end();
+        }
+        parts.push(&input[start..]);
+        parts
+    }
+
+    /// Splits in a single pass from left to right, at each position the
+    /// longest matching seperator splits
+    fn split_longest<'input>(&self, input: &'input str, seperators: &[String]) -> Vec<&'input str> {
+        let collapse = self.pattern.collapse_consecutive_separators;
+        let o = self.offset(input);
+        let mut parts = Vec::new();
+        let mut start = 0;
+        let mut last = None;
+        let mut i = 0;
+        while let Some(c) = input[i..].chars().next() {
+            let sep = if self.is_quoted(o + i) {
+                None
+            } else {
+                seperators
+                    .iter()
+                    .filter(|s| !s.is_empty() && input[i..].starts_with(s.as_str()))
+                    .max_by_key(|s| s.len())
+            };
+            if let Some(sep) = sep {
+                if self.deadline.passed(parts.len()) {
+                    return parts;
+                }
+                // with collapsing a run of the same seperator only splits once
+                if !(collapse && start == i && last == Some(sep)) {
+                    parts.push(&input[start..i]);
+                }
+                i += sep.len();
+                start = i;
+                last = Some(sep);
+            } else {
+                i += c.len_utf8();
+            }
+        }
+        parts.push(&input[start..]);
+        parts
+    }
+}
+
+/// Splits the input on all seperators, in a single pass unless seperators
+/// overlap. Stops early once the deadline passed.
+fn multi_split<'input>(
+    input: &'input str,
+    seperators: &Seperators,
+    deadline: &Deadline,
+) -> Vec<&'input str> {
+    match &seperators.list[..] {
+        [] => vec![input],
+        [s] => {
+            let mut parts = Vec::new();
+            for part in input.split(s.as_str()) {
+                if deadline.passed(parts.len()) {
+                    break;
+                }
+                parts.push(part);
+            }
+            parts
+        }
+        list if seperators.overlapping => sequential_split(input, list, deadline),
+        _ => scan_split(input, seperators, deadline),
+    }
+}
+
+/// Checks if any two seperators overlap, that is one contains the other
+/// or the end of one is the start of the other, or if one is empty. Only
+/// then splitting on each seperator in turn can differ from splitting at
+/// the first seperator that matches when scanning the input.
+fn overlapping(seperators: &[String]) -> bool {
+    let overlap = |a: &[u8], b: &[u8]| (1..a.len().min(b.len())).any(|k| a.ends_with(&b[..k]));
+    seperators.iter().enumerate().any(|(i, a)| {
+        a.is_empty()
+            || seperators[i + 1..].iter().any(|b| {
+                a.contains(b.as_str())
+                    || b.contains(a.as_str())
+                    || overlap(a.as_bytes(), b.as_bytes())
+                    || overlap(b.as_bytes(), a.as_bytes())
+            })
+    })
+}
+
+/// Splits the input in a single pass at each position a seperator starts
+/// at, the seperators must not be empty.
+///
+/// Seperators are valid UTF-8 so they neither start nor end inside a
+/// character of the input. Stops early once the deadline passed.
+fn scan_split<'input>(
+    input: &'input str,
+    seperators: &Seperators,
+    deadline: &Deadline,
+) -> Vec<&'input str> {
+    let first = &seperators.first;
+    let bytes = input.as_bytes();
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        if first[usize::from(bytes[i])] {
+            if let Some(s) = seperators
+                .list
+                .iter()
+                .find(|s| bytes[i..].starts_with(s.as_bytes()))
+            {
+                if deadline.passed(parts.len()) {
+                    return parts;
+                }
+                parts.push(&input[start..i]);
+                i += s.len();
+                start = i;
+                continue;
+            }
+        }
+        i += 1;
+    }
+    parts.push(&input[start..]);
+    parts
+}
+
+/// Splits the input on each seperator in turn, stops early once the
+/// deadline passed
+fn sequential_split<'input>(
+    input: &'input str,
+    seperators: &[String],
+    deadline: &Deadline,
+) -> Vec<&'input str> {
+    use std::mem;
+    let mut i: Vec<&str> = vec![input];
+    let mut i1 = vec![];
+    for s in seperators {
+        i1.clear();
+        for e in &i {
+            for part in e.split(s.as_str()) {
+                if deadline.passed(i1.len()) {
+                    return i1;
+                }
+                i1.push(part);
+            }
+        }
+        mem::swap(&mut i, &mut i1);
+    }
+    i
+}
+
+/// Maps a string that is either borrowed from the input or owned to a sub
+/// slice of it
+fn sub_cow<'input, F>(s: Cow<'input, str>, f: F) -> Cow<'input, str>
+where
+    F: for<'s> Fn(&'s str) -> &'s str,
+{
+    match s {
+        Cow::Borrowed(s) => Cow::Borrowed(f(s)),
+        Cow::Owned(s) => Cow::Owned(f(&s).to_string()),
+    }
+}
+
+/// Splits a string that is either borrowed from the input or owned
+fn split_cow<'input>(s: Cow<'input, str>, sep: &str) -> Vec<Cow<'input, str>> {
+    match s {
+        Cow::Borrowed(s) => s.split(sep).map(Cow::Borrowed).collect(),
+        Cow::Owned(s) => s.split(sep).map(|s| Cow::Owned(s.to_string())).collect(),
+    }
+}
+
+/// Finds the spans (offsets of the opening and closing quote) of all
+/// terminated quotes in the input, a backslash escapes a quote.
+fn quoted_spans(input: &str, quotes: &[char]) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut open: Option<(usize, char)> = None;
+    let mut escaped = false;
+    for (i, c) in input.char_indices() {
+        match open {
+            Some((start, q)) => {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' {
+                    escaped = true;
+                } else if c == q {
+                    spans.push((start, i));
+                    open = None;
+                }
+            }
+            None if quotes.contains(&c) => open = Some((i, c)),
+            None => (),
+        }
+    }
+    spans
+}
+
+/// Sorts spans and merges the ones that overlap, an offset is inside
+/// the merged spans exactly if it is inside one of the spans (both ends
+/// excluded)
+fn merge_spans(mut spans: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+    spans.sort_unstable();
+    let mut merged: Vec<(usize, usize)> = Vec::with_capacity(spans.len());
+    for (s, e) in spans {
+        match merged.last_mut() {
+            Some(last) if s < last.1 => last.1 = last.1.max(e),
+            _ => merged.push((s, e)),
+        }
+    }
+    merged
+}
+
+/// Finds the spans of escape characters and the characters they escape,
+/// an escape character at the end of the input is taken literally.
+fn escaped_spans(input: &str, escape: char) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut cs = input.char_indices();
+    while let Some((i, c)) = cs.next() {
+        if c == escape {
+            if let Some((j, e)) = cs.next() {
+                spans.push((i, j + e.len_utf8()));
+            }
+        }
+    }
+    spans
+}
+
+/// Removes escape characters, an escape character at the end is kept
+fn unescape(s: Cow<'_, str>, escape: char) -> Cow<'_, str> {
+    if !s.contains(escape) {
+        return s;
+    }
+    let mut r = String::with_capacity(s.len());
+    let mut cs = s.chars();
+    while let Some(c) = cs.next() {
+        if c == escape {
+            r.push(cs.next().unwrap_or(escape));
+        } else {
+            r.push(c);
+        }
+    }
+    Cow::Owned(r)
+}
+
+/// Finds the outermost balanced bracket spans of the input, brackets in
+/// quoted spans are ignored. The quoted spans are sorted and do not
+/// overlap, they are walked along with the input.
+fn bracket_spans(input: &str, quoted: &[(usize, usize)]) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut open: Vec<(usize, char)> = Vec::new();
+    let mut quoted = quoted.iter().peekable();
+    for (i, c) in input.char_indices() {
+        while quoted.next_if(|&&(_, e)| e <= i).is_some() {}
+        if quoted.peek().is_some_and(|&&(s, _)| s < i) {
+            continue;
+        }
+        let close = match c {
+            '(' | '[' | '{' | '<' => {
+                open.push((i, c));
+                continue;
+            }
+            ')' => '(',
+            ']' => '[',
+            '}' => '{',
+            '>' => '<',
+            _ => continue,
+        };
+        match open.pop() {
+            Some((start, o)) if o == close => {
+                if open.is_empty() {
+                    spans.push((start, i));
+                }
+            }
+            // a mismatched bracket unbalances everything opened so far
+            _ => open.clear(),
+        }
+    }
+    spans
+}
+
+/// Removes the brackets around a value that is bracketed as a whole
+fn unbracket(s: &str) -> &str {
+    let inner = match (s.chars().next(), s.chars().next_back()) {
+        (Some('('), Some(')'))
+        | (Some('['), Some(']'))
+        | (Some('{'), Some('}'))
+        | (Some('<'), Some('>')) => &s[1..s.len() - 1],
+        _ => return s,
+    };
+    // `(a) (b)` starts and ends with brackets but is not bracketed as a whole
+    if bracket_spans(s, &[]).first() == Some(&(0, s.len() - 1)) {
+        inner
+    } else {
+        s
+    }
+}
+
+/// Checks if a character is a control character or a unicode bidi control
+fn is_control(c: char) -> bool {
+    c.is_control()
+        || matches!(c, '\u{61c}' | '\u{200e}' | '\u{200f}' | '\u{202a}'..='\u{202e}' | '\u{2066}'..='\u{2069}')
+}
+
+/// Removes control characters, strings without them are returned as they
+/// are
+fn strip_control_chars(s: Cow<'_, str>) -> Cow<'_, str> {
+    if s.chars().any(is_control) {
+        Cow::Owned(s.chars().filter(|c| !is_control(*c)).collect())
+    } else {
+        s
+    }
+}
+
+/// Strips any number of seperators from the start and end of `s`
+fn strip_seperators<'input>(mut s: &'input str, seperators: &[String]) -> &'input str {
+    while let Some(r) = seperators
+        .iter()
+        .find_map(|sep| s.strip_prefix(sep.as_str()))
+    {
+        s = r;
+    }
+    while let Some(r) = seperators
+        .iter()
+        .find_map(|sep| s.strip_suffix(sep.as_str()))
+    {
+        s = r;
+    }
+    s
+}
+
+/// Removes the quotes around a key or value that is quoted as a whole.
+fn unquote<'input>(s: &'input str, quotes: &[char]) -> &'input str {
+    let mut cs = s.chars();
+    match (cs.next(), cs.next_back()) {
+        (Some(first), Some(last)) if first == last && quotes.contains(&first) => {
+            &s[first.len_utf8()..s.len() - last.len_utf8()]
+        }
+        _ => s,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use simd_json::borrowed::Object;
+    use simd_json::{BorrowedValue, OwnedValue};
+
+    #[test]
+    fn default() {
+        let d = Pattern::default();
+        let p = Pattern::compile("%{key}:%{val}").expect("compile");
+        assert_eq!(d, p);
+    }
+    #[test]
+    fn test_multisplit() {
+        let seps = vec![String::from(" "), String::from(";")];
+        let input = "this=is;a=test for:seperators";
+
+        let i = multi_split(input, &Seperators::from(seps), &Deadline::default());
+        assert_eq!(i, vec!["this=is", "a=test", "for:seperators"]);
+    }
+
+    #[test]
+    fn scan_split_matches_sequential_split() {
+        let cases: [&[&str]; 4] = [&[" ", ";"], &["&&", "||", ","], &["ä", "€"], &["aa", "b"]];
+        let inputs = [
+            "",
+            "a",
+            "a b;c;;d ",
+            "x&&y||z,,w&|v",
+            "käse€mäh€",
+            "aaab aaaa bab",
+        ];
+        for seps in cases {
+            let seps: Vec<String> = seps.iter().map(ToString::to_string).collect();
+            assert!(!overlapping(&seps));
+            for input in inputs {
+                assert_eq!(
+                    scan_split(input, &Seperators::from(seps.clone()), &Deadline::default()),
+                    sequential_split(input, &seps, &Deadline::default()),
+                    "{input:?} split on {seps:?}"
+                );
+            }
+        }
+        let overlaps: [&[&str]; 4] = [&["a", "xa"], &["ab", "bc"], &["=", "=="], &["", " "]];
+        for seps in overlaps {
+            let seps: Vec<String> = seps.iter().map(ToString::to_string).collect();
+            assert!(overlapping(&seps), "{seps:?}");
+        }
+        let seps = vec![String::from("a"), String::from("xa")];
+        assert_eq!(
+            multi_split("zxa", &Seperators::from(seps), &Deadline::default()),
+            ["zx", ""]
+        );
+    }
+    #[test]
+
+    fn simple_split() {
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        let r: BorrowedValue = kv.run("this=is a=test").expect("Failed to split input");
+        assert_eq!(r.as_object().map(Object::len).unwrap_or_default(), 2);
+        assert_eq!(r["this"], "is");
+        assert_eq!(r["a"], "test");
+    }
+
+    #[test]
+    fn simple_split2() {
+        let kv = Pattern::compile("&%{key}=%{val}").expect("Failed to build pattern");
+        let r: BorrowedValue = kv.run("this=is&a=test").expect("Failed to split input");
+        assert_eq!(r.as_object().map(Object::len).unwrap_or_default(), 2);
+        assert_eq!(r["this"], "is");
+        assert_eq!(r["a"], "test");
+    }
+    #[test]
+    fn newline_simple_() {
+        let kv = Pattern::compile(r"\n%{key}=%{val}").expect("Failed to build pattern");
+        let r: BorrowedValue = kv.run("this=is\na=test").expect("Failed to split input");
+        assert_eq!(r.as_object().map(Object::len).unwrap_or_default(), 2);
+        assert_eq!(r["this"], "is");
+        assert_eq!(r["a"], "test");
+    }
+
+    #[test]
+    fn simple_split3() {
+        let kv = Pattern::compile("&").expect("Failed to build pattern");
+        let r: BorrowedValue = kv.run("this:is&a:test").expect("Failed to split input");
+        assert_eq!(r.as_object().map(Object::len).unwrap_or_default(), 2);
+        assert_eq!(r["this"], "is");
+        assert_eq!(r["a"], "test");
+    }
+
+    #[test]
+    fn simple_split4() {
+        let kv = Pattern::compile("%{key}%{%{val}").expect("Failed to build pattern");
+        let r: BorrowedValue = kv.run("this%{is a%{test").expect("Failed to split input");
+        assert_eq!(r.as_object().map(Object::len).unwrap_or_default(), 2);
+        assert_eq!(r["this"], "is");
+        assert_eq!(r["a"], "test");
+    }
+
+    #[test]
+    fn simple_split5() {
+        let kv = Pattern::compile("%{key}%{key}%{val}").expect("Failed to build pattern");
+        dbg!(&kv);
+        let r: BorrowedValue = kv
+            .run("this%{key}is a%{key}test")
+            .expect("Failed to split input");
+        assert_eq!(r.as_object().map(Object::len).unwrap_or_default(), 2);
+        assert_eq!(r["this"], "is");
+        assert_eq!(r["a"], "test");
+    }
+
+    #[test]
+    fn invalid_pattern() {
+        let kv = Pattern::compile("%{key} ");
+        let e = kv.expect_err("no error");
+        assert_eq!(e, Error::InvalidPattern(6));
+        println!("{e}");
+
+        let kv = Pattern::compile("%{key} %{val} \\8");
+        let e = kv.expect_err("no error");
+        assert_eq!(e, Error::InvalidEscape('8'));
+        println!("{e}");
+
+        let kv = Pattern::compile("%{key} %{val} ");
+        let e = kv.expect_err("no error");
+        assert_eq!(e, Error::DoubleSeperator(String::from(" ")));
+        println!("{e}");
+
+        let kv = Pattern::compile("%{key}=%{val}; %{key}:%{val} %{key}:%{val}");
+        let e = kv.expect_err("no error");
+        assert_eq!(e, Error::DoubleSeperator(String::from(" ")));
+        println!("{e}");
+
+        let kv = Pattern::compile("%{key}=%{val};%{key}:%{val} :%{key}:%{val}");
+        let e = kv.expect_err("no error");
+        assert_eq!(e, Error::DoubleSeperator(String::from(":")));
+        println!("{e}");
+    }
+    #[test]
+    fn one_field() {
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        let r: BorrowedValue = kv.run("this=is").expect("Failed to split input");
+        assert_eq!(r.as_object().map(Object::len).unwrap_or_default(), 1);
+        assert_eq!(r["this"], "is");
+    }
+
+    #[test]
+    fn no_split() {
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        let r: Option<BorrowedValue> = kv.run("this is a test");
+        assert!(r.is_none());
+    }
+
+    #[test]
+    fn different_seperatpors() {
+        let kv = Pattern::compile("%{key}=%{val};%{key}:%{val} %{key}:%{val}")
+            .expect("Failed to build pattern");
+        dbg!(&kv);
+        let r: BorrowedValue = kv
+            .run("this=is;a=test for:seperators")
+            .expect("Failed to split input");
+        dbg!(&r);
+        assert_eq!(r.as_object().map(Object::len).unwrap_or_default(), 3);
+        assert_eq!(r["this"], "is");
+        assert_eq!(r["a"], "test");
+        assert_eq!(r["for"], "seperators");
+    }
+
+    #[test]
+    fn different_seperatpors2() {
+        let kv = Pattern::compile("%{key}=%{val}%{key}:%{val} %{key}:%{val};")
+            .expect("Failed to build pattern");
+        let r: BorrowedValue = kv
+            .run("this=is;a=test for:seperators")
+            .expect("Failed to split input");
+        dbg!(&r);
+        dbg!(&kv);
+        assert_eq!(r.as_object().map(Object::len).unwrap_or_default(), 3);
+        assert_eq!(r["this"], "is");
+        assert_eq!(r["a"], "test");
+        assert_eq!(r["for"], "seperators");
+    }
+
+    #[test]
+    fn quoted_keys() {
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_quotes(&['"']);
+        let r: BorrowedValue = kv
+            .run(r#""first name"=John "last=name"="Doe Jr" age=42"#)
+            .expect("Failed to split input");
+        assert_eq!(r.as_object().map(Object::len).unwrap_or_default(), 3);
+        assert_eq!(r["first name"], "John");
+        assert_eq!(r["last=name"], "Doe Jr");
+        assert_eq!(r["age"], "42");
+    }
+
+    #[test]
+    fn max_distinct_keys() {
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_max_distinct_keys(2);
+        let r: BorrowedValue = kv
+            .run("a=1 b=2 c=3 a=4 d=5 b=6")
+            .expect("Failed to split input");
+        assert_eq!(r.as_object().map(Object::len).unwrap_or_default(), 2);
+        assert_eq!(r["a"], "4");
+        assert_eq!(r["b"], "6");
+        assert!(r.get("c").is_none());
+        assert!(r.get("d").is_none());
+    }
+
+    #[test]
+    fn coerce_values() {
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_coerce_values(true);
+        let r: BorrowedValue = kv
+            .run(
+                "count=42 ratio=-2.5 exp=1e3 ok=true fail=false missing=null host=a1 version=1.2.3",
+            )
+            .expect("Failed to split input");
+        assert_eq!(r["count"].value_type(), ValueType::I64);
+        assert_eq!(r["count"], 42);
+        assert_eq!(r["ratio"].value_type(), ValueType::F64);
+        assert_eq!(r["ratio"], -2.5);
+        assert_eq!(r["exp"].value_type(), ValueType::F64);
+        assert_eq!(r["exp"], 1000.0);
+        assert_eq!(r["ok"].value_type(), ValueType::Bool);
+        assert_eq!(r["ok"], true);
+        assert_eq!(r["fail"], false);
+        assert!(r["missing"].is_null());
+        assert_eq!(r["host"].value_type(), ValueType::String);
+        assert_eq!(r["version"], "1.2.3");
+        let r: BorrowedValue = kv
+            .run("big=1e999 small=-1e999")
+            .expect("Failed to split input");
+        assert_eq!(r["big"], "1e999");
+        assert_eq!(r["small"], "-1e999");
+
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        let r: BorrowedValue = kv.run("count=42").expect("Failed to split input");
+        assert_eq!(r["count"].value_type(), ValueType::String);
+    }
+
+    #[test]
+    fn coerce_values_keeps_string_keys() {
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_coerce_values(true);
+        let r: OwnedValue = kv.run("true=false 1=2").expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"true": false, "1": 2}));
+        assert_eq!(r.get("true").map(ValueAsScalar::as_bool), Some(Some(false)));
+    }
+
+    #[test]
+    fn coercion_fallback() {
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_expected_type("port", ExpectedType::Integer)
+            .with_expected_type("tls", ExpectedType::Bool);
+        let r: BorrowedValue = kv.run("port=8080 tls=true").expect("Failed to split input");
+        assert_eq!(r["port"], 8080);
+        assert_eq!(r["tls"], true);
+
+        let input = "host=a port=80x";
+        let r: BorrowedValue = kv.run(input).expect("Failed to split input");
+        assert_eq!(r["port"], "80x");
+
+        let kv = kv.with_coercion_fallback(CoercionFallback::Null);
+        let r: BorrowedValue = kv.run(input).expect("Failed to split input");
+        assert!(r["port"].is_null());
+        assert_eq!(r["host"], "a");
+
+        let kv = kv.with_coercion_fallback(CoercionFallback::Drop);
+        let r: BorrowedValue = kv.run(input).expect("Failed to split input");
+        assert!(r.get("port").is_none());
+        assert_eq!(r["host"], "a");
+
+        let kv = kv.with_coercion_fallback(CoercionFallback::Error);
+        assert_eq!(kv.run::<BorrowedValue>(input), None);
+        let r: BorrowedValue = kv.run("host=a port=80").expect("Failed to split input");
+        assert_eq!(r["port"], 80);
+    }
+
+    #[test]
+    fn compile_from_header() {
+        let kv = Pattern::compile_from_header("#kv field_sep=, key_sep=:")
+            .expect("Failed to build pattern");
+        assert_eq!(
+            kv,
+            Pattern::compile(",%{key}:%{val}").expect("Failed to build pattern")
+        );
+        let mut records = Vec::new();
+        for line in ["a:1,b:2", "a:3,c:4"] {
+            let r: BorrowedValue = kv.run(line).expect("Failed to split input");
+            records.push(r);
+        }
+        assert_eq!(records[0]["a"], "1");
+        assert_eq!(records[0]["b"], "2");
+        assert_eq!(records[1]["a"], "3");
+        assert_eq!(records[1]["c"], "4");
+
+        let kv = Pattern::compile_from_header(r#"#kv field_sep=" " key_sep="=" field_sep=\t"#)
+            .expect("Failed to build pattern");
+        let r: BorrowedValue = kv.run("a=1\tb=2 c=3").expect("Failed to split input");
+        assert_eq!(r.as_object().map(Object::len).unwrap_or_default(), 3);
+
+        let e = Pattern::compile_from_header("kv field_sep=,").expect_err("no error");
+        assert_eq!(e, Error::InvalidHeader(String::from("kv field_sep=,")));
+        let e = Pattern::compile_from_header("#kv value_sep=,").expect_err("no error");
+        assert_eq!(e, Error::InvalidHeader(String::from("value_sep")));
+        let e = Pattern::compile_from_header("#kv field_sep=, junk").expect_err("no error");
+        assert_eq!(
+            e,
+            Error::InvalidHeader(String::from("#kv field_sep=, junk"))
+        );
+        let e = Pattern::compile_from_header("#kv field_sep=: key_sep=:").expect_err("no error");
+        assert_eq!(e, Error::DoubleSeperator(String::from(":")));
+    }
+
+    #[test]
+    fn collapse_consecutive_separators() {
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        let r: Option<BorrowedValue> = kv.run("a==b");
+        assert!(r.is_none());
+
+        let kv = kv.with_collapse_consecutive_separators(true);
+        let r: BorrowedValue = kv.run("a==b c===d").expect("Failed to split input");
+        assert_eq!(r.as_object().map(Object::len).unwrap_or_default(), 2);
+        assert_eq!(r["a"], "b");
+        assert_eq!(r["c"], "d");
+        // leading and trailing seperators still split off empty parts
+        let r: Option<BorrowedValue> = kv.run("=a=");
+        assert!(r.is_none());
+
+        let kv = Pattern::compile(",%{key}:%{val}")
+            .expect("Failed to build pattern")
+            .with_collapse_consecutive_separators(true);
+        let splitter = Splitter::new(&kv, "a:1,,,b:2");
+        assert_eq!(
+            splitter.split("a:1,,,b:2", &kv.field_seperators),
+            vec!["a:1", "b:2"]
+        );
+        let r: BorrowedValue = kv.run("a:1,,,b::2").expect("Failed to split input");
+        assert_eq!(r.as_object().map(Object::len).unwrap_or_default(), 2);
+        assert_eq!(r["a"], "1");
+        assert_eq!(r["b"], "2");
+    }
+
+    #[test]
+    fn matches() {
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        assert!(kv.matches("this=is a=test"));
+        assert!(kv.matches("this=is  a= "));
+        assert!(!kv.matches("this=is a test"));
+        assert!(!kv.matches("this=is a=b=c"));
+        assert!(!kv.matches(""));
+        assert!(!kv.matches("   "));
+        // a single pair is enough for run but not for matches
+        let r: Option<BorrowedValue> = kv.run("this=is a test");
+        assert!(r.is_some());
+    }
+
+    #[test]
+    #[cfg(feature = "simd-json")]
+    fn run_reader() {
+        use std::io::Cursor;
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        let input = Cursor::new(b"a=1 b=2\r\nnothing here\n\nc=3".to_vec());
+        let rs: Vec<_> = kv
+            .run_reader(input)
+            .collect::<io::Result<_>>()
+            .expect("Failed to read input");
+        assert_eq!(rs.len(), 4);
+        let r = rs[0].as_ref().expect("Failed to split input");
+        assert_eq!(r["a"], "1");
+        assert_eq!(r["b"], "2");
+        assert!(rs[1].is_none());
+        assert!(rs[2].is_none());
+        let r = rs[3].as_ref().expect("Failed to split input");
+        assert_eq!(r["c"], "3");
+
+        let input = Cursor::new(b"a=1\nb=\xff\nc=3\n".to_vec());
+        let rs: Vec<_> = kv.run_reader(input).collect();
+        assert_eq!(rs.len(), 3);
+        assert!(rs[0].is_ok());
+        assert_eq!(
+            rs[1].as_ref().map_err(io::Error::kind).err(),
+            Some(io::ErrorKind::InvalidData)
+        );
+        assert!(rs[2].is_ok());
+    }
+
+    #[test]
+    fn positional_keys() {
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_positional_keys(&["cmd", "arg"]);
+        let r: BorrowedValue = kv.run("foo bar k=v").expect("Failed to split input");
+        assert_eq!(r.as_object().map(Object::len).unwrap_or_default(), 3);
+        assert_eq!(r["cmd"], "foo");
+        assert_eq!(r["arg"], "bar");
+        assert_eq!(r["k"], "v");
+
+        // bare fields after the first pair and extra leading fields are dropped
+        let r: BorrowedValue = kv
+            .run("  foo bar baz k=v qux")
+            .expect("Failed to split input");
+        assert_eq!(r.as_object().map(Object::len).unwrap_or_default(), 3);
+        assert_eq!(r["cmd"], "foo");
+        assert_eq!(r["arg"], "bar");
+        let r: BorrowedValue = kv.run("k=v foo").expect("Failed to split input");
+        assert_eq!(r.as_object().map(Object::len).unwrap_or_default(), 1);
+    }
+
+    #[test]
+    fn leading_token_key() {
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_leading_token_key("app");
+        let r: OwnedValue = kv.run("myapp user=bob").expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"app": "myapp", "user": "bob"}));
+        let r: OwnedValue = kv
+            .run("  myapp \t user=bob  a=1")
+            .expect("Failed to split input");
+        assert_eq!(
+            r,
+            simd_json::json!({"app": "myapp", "user": "bob", "a": "1"})
+        );
+        let r: OwnedValue = kv.run("myapp").expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"app": "myapp"}));
+        let r: OwnedValue = kv.run("a=b c=d").expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"app": "a=b", "c": "d"}));
+    }
+
+    #[test]
+    fn respect_brackets() {
+        let kv = Pattern::compile("%{key}=%{val}, ").expect("Failed to build pattern");
+        let input = "call=func(a, b), list=[1, {x, y}], open=(a, b";
+        let r: BorrowedValue = kv.run(input).expect("Failed to split input");
+        assert_eq!(r["call"], "func(a");
+        let kv = kv.with_respect_brackets(true);
+        let r: BorrowedValue = kv.run(input).expect("Failed to split input");
+        assert_eq!(r["call"], "func(a, b)");
+        assert_eq!(r["list"], "[1, {x, y}]");
+        assert_eq!(r["open"], "(a");
+        let r: BorrowedValue = kv
+            .run("f(x=1)=2, g=(]), h=1")
+            .expect("Failed to split input");
+        assert_eq!(r["f(x=1)"], "2");
+        assert_eq!(r["g"], "(])");
+        assert_eq!(r["h"], "1");
+
+        let kv = kv.with_strip_brackets(true);
+        let r: OwnedValue = kv
+            .run("list=[1, 2], who=<a, b>, f=(a) (b), x=[], s=()")
+            .expect("Failed to split input");
+        assert_eq!(
+            r,
+            simd_json::json!({"list": "1, 2", "who": "a, b", "f": "(a) (b)", "x": "", "s": ""})
+        );
+    }
+
+    #[test]
+    fn pairs() {
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        let input = "a=1 bare b=2 c=3=4 a=5 e=";
+        let pairs: Vec<_> = kv.pairs(input).collect();
+        assert_eq!(pairs, [("a", "1"), ("b", "2"), ("a", "5"), ("e", "")]);
+        assert_eq!(kv.pairs(input).filter(|(k, _)| *k == "a").count(), 2);
+        assert_eq!(kv.pairs("").count(), 0);
+
+        let kv = kv.with_quotes(&['"']).with_positional_keys(&["cmd"]);
+        let pairs: Vec<_> = kv.pairs("get a=\"x y\" b=2").collect();
+        assert_eq!(pairs, [("cmd", "get"), ("a", "x y"), ("b", "2")]);
+
+        let kv = Pattern::compile("%{key}\t%{val}&").expect("Failed to build pattern");
+        assert_eq!(kv.pairs("\t").count(), 0);
+        for input in ["\t", " ", "", "a\t1&b\t2", "\t&a\t1", "a\t&\t1"] {
+            let pairs: Vec<_> = kv.pairs(input).collect();
+            let raw: Vec<_> = kv
+                .raw_pairs(input)
+                .into_iter()
+                .map(|p| (kv.raw_key(p.key), p.val))
+                .collect();
+            assert_eq!(pairs, raw, "{input:?}");
+        }
+
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        for policy in [
+            FlagPolicy::KeyAsValue,
+            FlagPolicy::True,
+            FlagPolicy::Collect("flags".to_string()),
+        ] {
+            let kv = kv.clone().with_flag_policy(policy);
+            let pairs: Vec<_> = kv.pairs("debug a=1").collect();
+            assert_eq!(pairs, [("a", "1")]);
+        }
+    }
+
+    #[test]
+    fn fill() {
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        let schema = ["host", "port", "user"];
+        let mut out = [None; 3];
+        kv.fill("port=80 host=a x=1 port=8080 bare", &schema, &mut out);
+        assert_eq!(out, [Some("a"), Some("8080"), None]);
+        kv.fill("user=bob", &schema, &mut out);
+        assert_eq!(out, [None, None, Some("bob")]);
+
+        let kv = kv.with_quotes(&['"']);
+        kv.fill("host=\"a b\" user=bob", &schema, &mut out);
+        assert_eq!(out, [Some("a b"), None, Some("bob")]);
+    }
+
+    #[test]
+    fn run_columns() {
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_positional_keys(&["cmd"]);
+        let (keys, vals) = kv.run_columns("get a=1 b=2 bare a=3");
+        assert_eq!(keys, ["cmd", "a", "b", "a"]);
+        assert_eq!(vals, ["get", "1", "2", "3"]);
+        let (keys, vals) = kv.run_columns("");
+        assert!(keys.is_empty() && vals.is_empty());
+    }
+
+    #[test]
+    fn run_sorted_by_value() {
+        let input = "b=10 a=9 c=x d=-1.5 e=10 f=abc";
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        assert_eq!(
+            kv.run_sorted_by_value(input),
+            [
+                ("d", "-1.5"),
+                ("b", "10"),
+                ("e", "10"),
+                ("a", "9"),
+                ("f", "abc"),
+                ("c", "x")
+            ]
+        );
+        let kv = kv.with_coerce_values(true);
+        assert_eq!(
+            kv.run_sorted_by_value(input),
+            [
+                ("d", "-1.5"),
+                ("a", "9"),
+                ("b", "10"),
+                ("e", "10"),
+                ("f", "abc"),
+                ("c", "x")
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "differ in length")]
+    fn fill_length_mismatch() {
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        kv.fill("a=1", &["a", "b"], &mut [None]);
+    }
+
+    #[test]
+    fn whitespace_field_separator() {
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_quotes(&['"'])
+            .with_whitespace_field_separator(true);
+        let r: BorrowedValue = kv
+            .run("a=1\tb=2 \t c=\"x\ty\"  \td=4\t")
+            .expect("Failed to split input");
+        assert_eq!(r.as_object().map(Object::len).unwrap_or_default(), 4);
+        assert_eq!(r["a"], "1");
+        assert_eq!(r["b"], "2");
+        assert_eq!(r["c"], "x\ty");
+        assert_eq!(r["d"], "4");
+        assert!(kv.matches("a=1\t\tb=2"));
+
+        let kv = Pattern::compile("%{key}=%{val}&")
+            .expect("Failed to build pattern")
+            .with_whitespace_field_separator(true);
+        let r: BorrowedValue = kv.run("a=1&b=2\tc=3").expect("Failed to split input");
+        assert_eq!(r["b"], "2");
+        assert_eq!(r["c"], "3");
+    }
+
+    #[test]
+    fn split_once() {
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        let r: OwnedValue = kv
+            .run("url=http://h?a=b x=1")
+            .expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"x": "1"}));
+        let kv = kv.with_split_once(true);
+        let r: OwnedValue = kv
+            .run("url=http://h?a=b x=1")
+            .expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"url": "http://h?a=b", "x": "1"}));
+        let r: Vec<_> = kv.pairs("a==b").collect();
+        assert_eq!(r, vec![("a", "=b")]);
+    }
+
+    #[test]
+    fn strip_trailing_separator() {
+        let input = "k==val== a=b=c x=1=";
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        assert_eq!(kv.run::<BorrowedValue>(input), None);
+
+        let kv = kv.with_strip_trailing_separator(true);
+        let r: BorrowedValue = kv.run(input).expect("Failed to split input");
+        assert_eq!(r["k"], "val");
+        assert_eq!(r["x"], "1");
+        assert!(r.get("a").is_none());
+
+        let kv = kv.with_collapse_consecutive_separators(true);
+        let r: BorrowedValue = kv.run(input).expect("Failed to split input");
+        assert_eq!(r["k"], "val");
+        assert_eq!(r["x"], "1");
+
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_collapse_consecutive_separators(true);
+        assert_eq!(kv.run::<BorrowedValue>(input), None);
+
+        let kv = kv.with_split_strategy(SplitStrategy::LongestMatch);
+        let kv = kv.with_strip_trailing_separator(true);
+        let r: BorrowedValue = kv.run("k==val==").expect("Failed to split input");
+        assert_eq!(r["k"], "val");
+    }
+
+    #[test]
+    fn flag_policy() {
+        let input = "red green mode=fast blue";
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        let r: OwnedValue = kv.run(input).expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"mode": "fast"}));
+
+        let kv = kv.with_flag_policy(FlagPolicy::KeyAsValue);
+        let r: OwnedValue = kv.run("red green blue").expect("Failed to split input");
+        assert_eq!(
+            r,
+            simd_json::json!({"red": "red", "green": "green", "blue": "blue"})
+        );
+        assert_eq!(kv.get_nth("red green", "green", 0), Some("green"));
+
+        let kv = kv
+            .with_flag_policy(FlagPolicy::Constant("set".to_string()))
+            .with_positional_keys(&["first"]);
+        let r: OwnedValue = kv.run(input).expect("Failed to split input");
+        assert_eq!(
+            r,
+            simd_json::json!({"first": "red", "green": "set", "mode": "fast", "blue": "set"})
+        );
+
+        let kv = kv
+            .with_flag_policy(FlagPolicy::True)
+            .with_positional_keys(&[]);
+        let r: OwnedValue = kv
+            .run("debug verbose mode=fast")
+            .expect("Failed to split input");
+        assert_eq!(
+            r,
+            simd_json::json!({"debug": true, "verbose": true, "mode": "fast"})
+        );
+
+        let kv = kv.with_flag_policy(FlagPolicy::Collect("flags".to_string()));
+        let r: OwnedValue = kv
+            .run("debug verbose mode=fast")
+            .expect("Failed to split input");
+        assert_eq!(
+            r,
+            simd_json::json!({"flags": ["debug", "verbose"], "mode": "fast"})
+        );
+        let r: OwnedValue = kv.run("debug mode=fast").expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"flags": ["debug"], "mode": "fast"}));
+    }
+
+    #[test]
+    fn annotate_input() {
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        assert_eq!(kv.annotate_input("a=1 b=2"), "a[=]1| |b[=]2");
+        assert_eq!(kv.annotate_input("a=1=2 bare "), "a[=]1[=]2| |bare| |");
+        assert_eq!(kv.annotate_input(""), "");
+
+        let kv = Pattern::compile("%{key}=%{val}&%{key}:%{val}")
+            .expect("Failed to build pattern")
+            .with_quotes(&['"'])
+            .with_collapse_consecutive_separators(true);
+        assert_eq!(
+            kv.annotate_input("a==\"x&y\"&&b:2"),
+            "a[==]\"x&y\"|&&|b[:]2"
+        );
+    }
+
+    #[test]
+    fn strip_control_chars() {
+        let input = "user=admin\u{202e}nimda\u{202c} msg=a\u{7}b k\u{200f}ey=1";
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        let r: BorrowedValue = kv.run(input).expect("Failed to split input");
+        assert_eq!(r["user"], "admin\u{202e}nimda\u{202c}");
+
+        let kv = kv.with_strip_control_chars(true);
+        let r: BorrowedValue = kv.run(input).expect("Failed to split input");
+        assert_eq!(r["user"], "adminnimda");
+        assert_eq!(r["msg"], "ab");
+        assert_eq!(r["k\u{200f}ey"], "1");
+
+        let kv = kv.with_strip_control_chars_in_keys(true);
+        let r: BorrowedValue = kv.run(input).expect("Failed to split input");
+        assert_eq!(r["key"], "1");
+    }
+
+    #[test]
+    fn nested_values() {
+        let kv = Pattern::compile("%{key}=%{val} %{key}:%{val}")
+            .expect("Failed to build pattern")
+            .with_quotes(&['"'])
+            .with_coerce_values(true);
+        let input = "meta=type:error a=1 b:2 c=x:y:z q=\"k\":\"v 1\"";
+        let r: OwnedValue = kv.run(input).expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"a": 1, "b": 2}));
+
+        let kv = kv.with_nested_values(true);
+        let r: OwnedValue = kv.run(input).expect("Failed to split input");
+        assert_eq!(
+            r,
+            simd_json::json!({"meta": {"type": "error"}, "a": 1, "b": 2, "q": {"k": "v 1"}})
+        );
+        let r: OwnedValue = kv.run("n=count=3").expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"n": {"count": 3}}));
+        assert_eq!(kv.get_nth(input, "meta", 0), Some("type:error"));
+    }
+
+    #[test]
+    fn escape_char() {
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_escape_char('\\');
+        let input = r"a\=b=c d=x\ y e=\\ f=1\";
+        let r: OwnedValue = kv.run(input).expect("Failed to split input");
+        assert_eq!(
+            r,
+            simd_json::json!({"a=b": "c", "d": "x y", "e": "\\", "f": "1\\"})
+        );
+        assert_eq!(kv.get_nth(input, "d", 0), Some(r"x\ y"));
+        assert_eq!(kv.annotate_input(r"a\=b=c"), r"a\=b[=]c");
+
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        assert_eq!(kv.run::<OwnedValue>(r"a\=b=c"), None);
+    }
+
+    #[test]
+    fn then() {
+        let inner = Pattern::compile("%{key}:%{val},").expect("Failed to build pattern");
+        let kv = Pattern::compile("%{key}=%{val}&")
+            .expect("Failed to build pattern")
+            .with_coerce_values(true);
+        let input = "a=x:1,y:2&b=plain&c=z:true";
+        let r: OwnedValue = kv.run(input).expect("Failed to split input");
+        assert_eq!(r["a"], "x:1,y:2");
+
+        let kv = kv.then(inner.clone().with_coerce_values(true));
+        let r: OwnedValue = kv.run(input).expect("Failed to split input");
+        assert_eq!(
+            r,
+            simd_json::json!({"a": {"x": 1, "y": 2}, "b": "plain", "c": {"z": true}})
+        );
+
+        let kv = Pattern::compile("%{key}=%{val}&")
+            .expect("Failed to build pattern")
+            .then(inner.then(Pattern::compile("%{key}/%{val}").expect("Failed to build pattern")));
+        let r: OwnedValue = kv.run("a=x:k/v,y:2").expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"a": {"x": {"k": "v"}, "y": "2"}}));
+
+        let kv = kv.with_escape_char('\\');
+        let r: OwnedValue = kv.run(r"a=x:k\&/v").expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"a": {"x": {"k&": "v"}}}));
+    }
+
+    #[test]
+    fn max_total_depth() {
+        let inner = Pattern::compile("%{key}:%{val},")
+            .expect("Failed to build pattern")
+            .then(Pattern::compile("%{key}/%{val}").expect("Failed to build pattern"));
+        let kv = Pattern::compile("%{key}=%{val}&")
+            .expect("Failed to build pattern")
+            .with_nested_values(true)
+            .then(inner);
+        let input = "a=x:k/v&b=1";
+        let r: OwnedValue = kv.run(input).expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"a": {"x": {"k": "v"}}, "b": "1"}));
+
+        let kv = kv.with_max_total_depth(2);
+        let r: Option<OwnedValue> = kv.run_checked(input).expect("Failed to split input");
+        assert_eq!(
+            r,
+            Some(simd_json::json!({"a": {"x": {"k": "v"}}, "b": "1"}))
+        );
+        let r: OwnedValue = kv.run("n=m=3&b=1").expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"n": {"m": "3"}, "b": "1"}));
+
+        let kv = kv.with_max_total_depth(1);
+        assert_eq!(kv.run::<OwnedValue>(input), None);
+        assert_eq!(
+            kv.run_checked::<OwnedValue>(input),
+            Err(Error::MaxDepthExceeded(1))
+        );
+        let r: Option<OwnedValue> = kv.run_checked("a=x:1&b=1").expect("Failed to split input");
+        assert_eq!(r, Some(simd_json::json!({"a": {"x": "1"}, "b": "1"})));
+
+        let kv = kv.with_max_total_depth(0);
+        assert_eq!(
+            kv.run_checked::<OwnedValue>("n=m=3"),
+            Err(Error::MaxDepthExceeded(0))
+        );
+        let r: Option<OwnedValue> = kv.run_checked("b=1").expect("Failed to split input");
+        assert_eq!(r, Some(simd_json::json!({"b": "1"})));
+    }
+
+    #[test]
+    fn quotes() {
+        let input = r#"msg="hello world" level=info who='a b' q="k=v""#;
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_quotes(&DEFAULT_QUOTES);
+        let r: OwnedValue = kv.run(input).expect("Failed to split input");
+        assert_eq!(
+            r,
+            simd_json::json!({"msg": "hello world", "level": "info", "who": "a b", "q": "k=v"})
+        );
+        let r: OwnedValue = kv
+            .with_keep_quotes(true)
+            .run(input)
+            .expect("Failed to split input");
+        assert_eq!(
+            r,
+            simd_json::json!({"msg": "\"hello world\"", "level": "info", "who": "'a b'", "q": "\"k=v\""})
+        );
+    }
+
+    /// The fastest of a few runs of `f`
+    fn fastest<F: FnMut()>(mut f: F) -> std::time::Duration {
+        (0..5)
+            .map(|_| {
+                let start = Instant::now();
+                f();
+                start.elapsed()
+            })
+            .min()
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn quoted_scaling() {
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_quotes(&['"']);
+        let input = |n: usize| "k=\"v\" ".repeat(n);
+        let (small, large) = (input(2000), input(16000));
+        let t_small = fastest(|| assert!(kv.run::<BorrowedValue>(&small).is_some()));
+        let t_large = fastest(|| assert!(kv.run::<BorrowedValue>(&large).is_some()));
+        // 8 times the input, splitting in quadratic time takes 64 times as long
+        assert!(
+            t_large < t_small * 24,
+            "{t_large:?} for 8 times the input of {t_small:?}"
+        );
+    }
+
+    #[test]
+    fn bracket_scaling() {
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_quotes(&['"'])
+            .with_respect_brackets(true);
+        let input = |n: usize| "k=\"v\" b=[x y] ".repeat(n);
+        let (small, large) = (input(1000), input(8000));
+        let t_small = fastest(|| assert!(kv.run::<BorrowedValue>(&small).is_some()));
+        let t_large = fastest(|| assert!(kv.run::<BorrowedValue>(&large).is_some()));
+        assert!(
+            t_large < t_small * 24,
+            "{t_large:?} for 8 times the input of {t_small:?}"
+        );
+    }
+
+    #[test]
+    fn recursive() {
+        let kv = Pattern::compile("%{key}=%{val},%{key}:%{val}")
+            .expect("Failed to build pattern")
+            .with_quotes(&['"', '\''])
+            .with_recursive(true);
+        let r: OwnedValue = kv
+            .run(r#"outer="inner1:a,inner2:'x=1,y=2'",plain=b c,n=1"#)
+            .expect("Failed to split input");
+        assert_eq!(
+            r,
+            simd_json::json!({
+                "outer": {"inner1": "a", "inner2": {"x": "1", "y": "2"}},
+                "plain": "b c",
+                "n": "1"
+            })
+        );
+
+        let input = r#"a="b='c=1'""#;
+        let r: OwnedValue = kv
+            .clone()
+            .with_max_recursion_depth(1)
+            .run(input)
+            .expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"a": {"b": "c=1"}}));
+        let r: OwnedValue = kv
+            .clone()
+            .with_max_recursion_depth(0)
+            .run(input)
+            .expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"a": "b='c=1'"}));
+        assert_eq!(
+            kv.with_max_total_depth(1).run_checked::<OwnedValue>(input),
+            Err(Error::MaxDepthExceeded(1))
+        );
+    }
+
+    #[test]
+    fn trim_first_field() {
+        let input = "\u{feff}  a=1, b= 2";
+        let kv = Pattern::compile("%{key}=%{val},").expect("Failed to build pattern");
+        let r: OwnedValue = kv.run(input).expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"\u{feff}  a": "1", " b": " 2"}));
+
+        let kv = kv.with_trim_first_field(true);
+        let r: OwnedValue = kv.run(input).expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"a": "1", " b": " 2"}));
+        let mut out = [None];
+        kv.fill(input, &["a"], &mut out);
+        assert_eq!(out, [Some("1")]);
+    }
+
+    #[test]
+    fn display() {
+        for (pattern, display) in [
+            ("%{key}=%{val}", "%{key}=%{val} "),
+            ("&%{key}=%{val}", "%{key}=%{val}&"),
+            (
+                "%{key}=%{val}&%{key}:%{val}",
+                "%{key}:%{val}&%{key}=%{val}&",
+            ),
+            ("%{key}\\t%{val}\\n", "%{key}\\t%{val}\\n"),
+            (
+                "%{key}\\\\%{val} %{key}\\\\%{val};",
+                "%{key}\\\\%{val} %{key}\\\\%{val};",
+            ),
+        ] {
+            let kv = Pattern::compile(pattern).expect("Failed to build pattern");
+            assert_eq!(kv.to_string(), display);
+            assert_eq!(Pattern::compile(display), Ok(kv));
+        }
+        let kv = Pattern::compile("%{key}=%{val}&%{key}:%{val}").expect("Failed to build pattern");
+        assert_eq!(kv.field_separators(), ["&"]);
+        assert_eq!(kv.key_separators(), [":", "="]);
+        assert!(kv.quotes().is_empty());
+    }
+
+    #[test]
+    fn from_str() {
+        let kv: Pattern = "%{key}=%{val}&".parse().expect("Failed to build pattern");
+        assert_eq!(Pattern::compile("%{key}=%{val}&"), Ok(kv.clone()));
+        assert_eq!(Pattern::try_from("%{key}=%{val}&"), Ok(kv));
+        assert_eq!(
+            "%{key}%{val}".parse::<Pattern>(),
+            Err(Error::MissingKeySeperator(6))
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "simd-json")]
+    fn run_owned() {
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_coerce_values(true);
+        let r = {
+            let line = String::from("a=1 b=x a=2");
+            kv.run_owned(&line)
+        };
+        assert_eq!(r, Some(simd_json::json!({"a": 2, "b": "x"})));
+        let m = kv
+            .run_string_map("a=1 b=x a=2")
+            .expect("Failed to split input");
+        assert_eq!(m.len(), 2);
+        assert_eq!(m["a"], "2");
+        assert_eq!(kv.run_string_map("nothing"), None);
+    }
+
+    #[test]
+    fn run_map() {
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_positional_keys(&["first"]);
+        let m = kv.run_map("x a=1 b=2 a=3").expect("Failed to split input");
+        assert_eq!(m, HashMap::from([("first", "x"), ("a", "3"), ("b", "2")]));
+        assert_eq!(kv.run_map(""), None);
+    }
+
+    #[test]
+    fn run_into() {
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        let mut event = simd_json::json!({"host": "a", "x": "0"});
+        assert_eq!(kv.run_into("x=1 y=2", &mut event), Ok(2));
+        assert_eq!(event, simd_json::json!({"host": "a", "x": "1", "y": "2"}));
+        assert_eq!(kv.run_into("nothing", &mut event), Ok(0));
+        let mut array = simd_json::json!([]);
+        assert_eq!(kv.run_into("x=1", &mut array), Err(Error::NotAnObject));
+    }
+
+    #[test]
+    fn compile_strict() {
+        assert_eq!(
+            Pattern::compile_strict("%{key}=%{val}"),
+            Err(Error::NoFieldSeperator("%{key}=%{val}".to_string()))
+        );
+        assert_eq!(
+            Pattern::compile_strict("&"),
+            Err(Error::NoKeySeperator("&".to_string()))
+        );
+        assert_eq!(
+            Pattern::compile_strict(""),
+            Err(Error::NoFieldSeperator(String::new()))
+        );
+        assert_eq!(
+            Pattern::compile_strict("%{key}"),
+            Err(Error::InvalidPattern(6))
+        );
+        let kv = Pattern::compile_strict("%{key}=%{val}&").expect("Failed to build pattern");
+        assert_eq!(
+            kv,
+            Pattern::compile("%{key}=%{val}&").expect("Failed to build pattern")
+        );
+        assert!(Pattern::compile("%{key}=%{val}").is_ok());
+        assert!(Pattern::compile("&").is_ok());
+    }
+
+    #[test]
+    fn whole_as_value_key() {
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_whole_as_value_key("message");
+        let r: OwnedValue = kv.run(" service started ").expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"message": "service started"}));
+        let r: OwnedValue = kv.run("a=1 bare").expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"a": "1"}));
+        assert_eq!(kv.run::<OwnedValue>("  "), None);
+    }
+
+    #[test]
+    fn dedupe_identical() {
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_duplicates(DuplicatePolicy::Array)
+            .with_coerce_values(true);
+        let r: OwnedValue = kv.run("x=1 x=1 x=2").expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"x": [1, 1, 2]}));
+        let kv = kv.with_dedupe_identical(true);
+        let r: OwnedValue = kv
+            .run("x=1 x=1 x=2 y=1 y=1")
+            .expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"x": [1, 2], "y": 1}));
+    }
+
+    #[test]
+    fn include_exclude_keys() {
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_include_keys(&["hits", "status"]);
+        let r: OwnedValue = kv
+            .run("hits=3 path=/ status=200 qt=9")
+            .expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"hits": "3", "status": "200"}));
+        assert_eq!(kv.run::<OwnedValue>("path=/ qt=9"), None);
+        let kv = kv.with_include_keys(&[]).with_exclude_keys(&["qt"]);
+        let r: OwnedValue = kv.run("hits=3 qt=9").expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"hits": "3"}));
+    }
+
+    #[test]
+    fn trim_key_value() {
+        let kv = Pattern::compile("%{key}=%{val};").expect("Failed to build pattern");
+        let r: OwnedValue = kv.run("key = value ;a=*1*").expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"key ": " value ", "a": "*1*"}));
+        let kv = kv
+            .with_trim_key(Trim::default())
+            .with_trim_value(Trim::Chars(vec![' ', '*']));
+        let r: OwnedValue = kv.run("key = value ;a=*1*").expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"key": "value", "a": "1"}));
+    }
+
+    #[test]
+    fn key_prefix() {
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_key_prefix("kv_")
+            .with_exclude_keys(&["b"]);
+        let r: OwnedValue = kv.run("a=1 b=2").expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"kv_a": "1"}));
+    }
+
+    #[test]
+    fn nested_keys() {
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_nested_keys(true);
+        let r: OwnedValue = kv
+            .run("http.status=200 http.method=GET")
+            .expect("Failed to split input");
+        assert_eq!(
+            r,
+            simd_json::json!({"http": {"status": "200", "method": "GET"}})
+        );
+        let kv = kv.with_nested_keys(false);
+        let r: OwnedValue = kv.run("http.status=200").expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"http.status": "200"}));
+    }
+
+    #[test]
+    fn nesting_separator() {
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_nesting_separator(".");
+        let r: OwnedValue = kv
+            .run("http.status=200 http.method=GET a.0=x a.b.c=1 a.b=2 x=1")
+            .expect("Failed to split input");
+        assert_eq!(
+            r,
+            simd_json::json!({
+                "http": {"status": "200", "method": "GET"},
+                "a": {"0": "x", "b": "2"},
+                "x": "1"
+            })
+        );
+
+        let kv = kv.with_nesting_separator("");
+        assert_eq!(
+            kv,
+            Pattern::compile("%{key}=%{val}").expect("Failed to build pattern")
+        );
+        let r: OwnedValue = kv.run("http.status=200").expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"http.status": "200"}));
+    }
+
+    #[test]
+    fn array_index_notation() {
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_nesting_separator(".")
+            .with_array_index_notation(true);
+        let r: OwnedValue = kv.run("a.0=x a.2=z").expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"a": ["x", null, "z"]}));
+        let r: OwnedValue = kv
+            .run("l.1.n=b l.0.n=a m.0.0=1 m.1=2 big.99999=x")
+            .expect("Failed to split input");
+        assert_eq!(
+            r,
+            simd_json::json!({
+                "l": [{"n": "a"}, {"n": "b"}],
+                "m": [["1"], "2"],
+                "big": {"99999": "x"}
+            })
+        );
+
+        let kv = kv.with_index_gaps(IndexGapPolicy::Error);
+        assert_eq!(kv.run::<OwnedValue>("a.0=x a.2=z"), None);
+        let r: OwnedValue = kv.run("a.1=y a.0=x").expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"a": ["x", "y"]}));
+    }
+
+    #[test]
+    fn whitespace_only_input() {
+        let patterns = [
+            Pattern::compile("%{key}=%{val}").expect("Failed to build pattern"),
+            Pattern::compile(",%{key}=%{val}")
+                .expect("Failed to build pattern")
+                .with_positional_keys(&["first"]),
+            Pattern::compile(",%{key} %{val}").expect("Failed to build pattern"),
+            Pattern::compile(",%{key}\t%{val}")
+                .expect("Failed to build pattern")
+                .with_quotes(&['"'])
+                .with_collapse_consecutive_separators(true)
+                .with_coerce_values(true),
+        ];
+        for kv in &patterns {
+            for input in ["", " ", "   ", "\t \n"] {
+                let r: Option<BorrowedValue> = kv.run(input);
+                assert!(r.is_none(), "{input:?} should not parse with {kv:?}");
+                assert!(!kv.matches(input));
+            }
+        }
+    }
+
+    #[test]
+    fn duplicates() {
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        let r: BorrowedValue = kv.run("n=1 m=a n=2").expect("Failed to split input");
+        assert_eq!(r["n"], "2");
+
+        let kv = kv.with_duplicates(DuplicatePolicy::Array);
+        let r: BorrowedValue = kv.run("n=1 m=a n=2 n=3").expect("Failed to split input");
+        assert_eq!(r.as_object().map(Object::len).unwrap_or_default(), 2);
+        assert_eq!(r["n"], BorrowedValue::from(vec!["1", "2", "3"]));
+        assert_eq!(r["m"], "a");
+    }
+
+    #[test]
+    fn coerced_duplicates() {
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_duplicates(DuplicatePolicy::Array)
+            .with_coerce_values(true);
+        let r: BorrowedValue = kv.run("n=1 n=2 x=a x=true").expect("Failed to split input");
+        let n = r["n"].as_array().expect("not an array");
+        assert_eq!(n.len(), 2);
+        assert!(n.iter().all(|v| v.value_type() == ValueType::I64));
+        assert_eq!(r["n"], BorrowedValue::from(vec![1, 2]));
+        let x = r["x"].as_array().expect("not an array");
+        assert_eq!(x[0], "a");
+        assert_eq!(x[1], true);
+
+        let r: simd_json::OwnedValue = kv.run("n=1 n=2.5").expect("Failed to split input");
+        assert_eq!(r["n"][0].value_type(), ValueType::I64);
+        assert_eq!(r["n"][1].value_type(), ValueType::F64);
+    }
+
+    #[test]
+    fn min_max_duplicates() {
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_coerce_values(true)
+            .with_duplicates(DuplicatePolicy::Max);
         let r: BorrowedValue = kv
-            .run("this=is;a=test for:seperators")
+            .run("n=5 n=3 n=9 n=7 f=1.5 f=2 f=-1 s=a s=b")
             .expect("Failed to split input");
-        dbg!(&r);
-        dbg!(&kv);
-        assert_eq!(r.as_object().map(Object::len).unwrap_or_default(), 3);
-        assert_eq!(r["this"], "is");
-        assert_eq!(r["a"], "test");
-        assert_eq!(r["for"], "seperators");
+        assert_eq!(r["n"], 9);
+        assert_eq!(r["f"], 2);
+        assert_eq!(r["s"], "b");
+
+        let kv = kv.with_duplicates(DuplicatePolicy::Min);
+        let r: BorrowedValue = kv
+            .run("n=5 n=3 n=9 n=7 f=1.5 f=2 f=-1.5 s=a s=b")
+            .expect("Failed to split input");
+        assert_eq!(r["n"], 3);
+        assert_eq!(r["f"], -1.5);
+        assert_eq!(r["s"], "b");
+    }
+
+    #[test]
+    fn fingerprint() {
+        let p1 = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        let p2 = Pattern::compile("%{key}=%{val} %{key}=%{val}").expect("Failed to build pattern");
+        let p3 = Pattern::compile("%{key}:%{val}").expect("Failed to build pattern");
+        assert_eq!(p1.fingerprint(), p2.fingerprint());
+        assert_eq!(p1.fingerprint(), p1.clone().fingerprint());
+        assert_ne!(p1.fingerprint(), p3.fingerprint());
+        assert_ne!(
+            p1.fingerprint(),
+            p1.clone().with_coerce_values(true).fingerprint()
+        );
+        assert_eq!(Pattern::default().fingerprint(), p3.fingerprint());
+        // the same with and without features
+        assert_eq!(p1.fingerprint(), 12_500_924_083_994_710_650);
+        assert_eq!(Pattern::default().fingerprint(), 15_837_416_414_986_204_223);
+    }
+
+    #[test]
+    fn value_arrays() {
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_value_array_separator(",");
+        let r: BorrowedValue = kv
+            .run("tags=a,b,c single=d")
+            .expect("Failed to split input");
+        assert_eq!(r["tags"], BorrowedValue::from(vec!["a", "b", "c"]));
+        assert_eq!(r["single"], "d");
+
+        let kv = kv.with_single_value_arrays(true).with_coerce_values(true);
+        let r: BorrowedValue = kv
+            .run("ports=80,443 single=d")
+            .expect("Failed to split input");
+        assert_eq!(r["ports"], BorrowedValue::from(vec![80, 443]));
+        assert_eq!(r["single"], BorrowedValue::from(vec!["d"]));
+
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_value_array_separator("");
+        assert_eq!(
+            kv,
+            Pattern::compile("%{key}=%{val}").expect("Failed to build pattern")
+        );
+        let r: BorrowedValue = kv.run("tags=ab").expect("Failed to split input");
+        assert_eq!(r["tags"], "ab");
+    }
+
+    #[test]
+    fn value_separator_mapping() {
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_value_separator_mapping(";", ",")
+            .with_value_array_separator(",");
+        let r: OwnedValue = kv
+            .run("tags=a;b,c single=d;e")
+            .expect("Failed to split input");
+        assert_eq!(
+            r,
+            simd_json::json!({"tags": ["a", "b", "c"], "single": ["d", "e"]})
+        );
+
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_value_separator_mapping("", ",");
+        assert_eq!(
+            kv,
+            Pattern::compile("%{key}=%{val}").expect("Failed to build pattern")
+        );
+        let r: OwnedValue = kv.run("tags=ab").expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"tags": "ab"}));
+    }
+
+    #[test]
+    fn pattern_shapes() {
+        // documented defaults
+        assert_eq!(Pattern::compile("").expect("empty"), Pattern::default());
+        let r: BorrowedValue = Pattern::compile("")
+            .expect("empty")
+            .run("a:1 b:2")
+            .expect("Failed to split input");
+        assert_eq!(r.as_object().map(Object::len).unwrap_or_default(), 2);
+        assert_eq!(
+            Pattern::compile("&;").expect("only seperators"),
+            Pattern::compile("&;%{key}:%{val}").expect("Failed to build pattern")
+        );
+        assert_eq!(
+            Pattern::compile("%{key}=%{val}%{key}=%{val}").expect("repeated"),
+            Pattern::compile("%{key}=%{val}").expect("Failed to build pattern")
+        );
+        // errors
+        for (pattern, e) in [
+            ("%{key}", Error::InvalidPattern(6)),
+            ("%{key}%{key}", Error::InvalidPattern(6)),
+            ("%{val}", Error::UnexpectedVal(0)),
+            ("%{val}%{key}", Error::UnexpectedVal(0)),
+            ("&%{val}&", Error::UnexpectedVal(1)),
+            ("%{key}=%{val} %{val}", Error::UnexpectedVal(14)),
+            ("%{key}%{val}", Error::MissingKeySeperator(6)),
+            (" %{key}%{val}", Error::MissingKeySeperator(7)),
+        ] {
+            assert_eq!(Pattern::compile(pattern), Err(e), "{pattern}");
+        }
+        println!("{}", Error::UnexpectedVal(0));
+        println!("{}", Error::MissingKeySeperator(6));
+    }
+
+    #[test]
+    fn run_filter() {
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        let r: BorrowedValue = kv
+            .run_filter("a=1 b= c=x d=", |_, v| !v.is_empty())
+            .expect("Failed to split input");
+        assert_eq!(r.as_object().map(Object::len).unwrap_or_default(), 2);
+        assert_eq!(r["a"], "1");
+        assert_eq!(r["c"], "x");
+
+        let kv = kv.with_coerce_values(true);
+        let r: BorrowedValue = kv
+            .run_filter("a=1 b=x c=2.5", |_, v| v.parse::<f64>().is_ok())
+            .expect("Failed to split input");
+        assert_eq!(r.as_object().map(Object::len).unwrap_or_default(), 2);
+        assert_eq!(r["a"], 1);
+        assert_eq!(r["c"], 2.5);
+
+        let r: Option<BorrowedValue> = kv.run_filter("a=1 b=2", |k, _| k == "c");
+        assert!(r.is_none());
+    }
+
+    #[test]
+    fn get_nth() {
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_quotes(&['"'])
+            .with_coerce_values(true);
+        let input = r#"a=1 b=2 a="x y" a=3"#;
+        assert_eq!(kv.get_nth(input, "a", 0), Some("1"));
+        assert_eq!(kv.get_nth(input, "a", 1), Some("x y"));
+        assert_eq!(kv.get_nth(input, "a", 2), Some("3"));
+        assert_eq!(kv.get_nth(input, "a", 3), None);
+        assert_eq!(kv.get_nth(input, "b", 0), Some("2"));
+        assert_eq!(kv.get_nth(input, "c", 0), None);
+    }
+
+    #[test]
+    fn max_pairs() {
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_max_pairs(2);
+        let r: BorrowedValue = kv
+            .run("a=1 garbage b=2 c=3")
+            .expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"a": "1", "b": "2"}));
+        assert_eq!(kv.pairs("a=1 b=2 c=3").count(), 2);
+        let r: BorrowedValue = kv
+            .run_limited("a=1 b=2 c=3", 1)
+            .expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"a": "1"}));
+        let r: BorrowedValue = kv
+            .run_limited("a=1 b=2 c=3", 5)
+            .expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"a": "1", "b": "2"}));
+        assert_eq!(kv.run_limited::<BorrowedValue>("a=1", 0), None);
+    }
+
+    #[test]
+    fn limits() {
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        let input = "a=1 bb=22 ccc=333";
+        let checked = |kv: Pattern| kv.run_checked::<BorrowedValue>(input).map(|r| r.is_some());
+        assert_eq!(checked(kv.clone().with_max_input_length(17)), Ok(true));
+        assert_eq!(
+            checked(kv.clone().with_max_input_length(16)),
+            Err(Error::LimitExceeded(Limit::InputLength(16)))
+        );
+        assert_eq!(checked(kv.clone().with_max_fields(3)), Ok(true));
+        assert_eq!(
+            checked(kv.clone().with_max_fields(2)),
+            Err(Error::LimitExceeded(Limit::Fields(2)))
+        );
+        assert_eq!(
+            checked(kv.clone().with_max_key_length(2)),
+            Err(Error::LimitExceeded(Limit::KeyLength(2)))
+        );
+        assert_eq!(
+            checked(kv.clone().with_max_value_length(2)),
+            Err(Error::LimitExceeded(Limit::ValueLength(2)))
+        );
+        assert_eq!(
+            kv.clone().with_max_fields(2).run::<BorrowedValue>(input),
+            None
+        );
+        assert_eq!(
+            kv.with_max_value_length(2)
+                .try_run::<BorrowedValue>(input)
+                .map_err(|e| e.to_string()),
+            Err("no pairs, value too long in 'ccc=333' at byte 10".to_string())
+        );
+    }
+
+    #[test]
+    fn run_with_deadline() {
+        use std::time::Duration;
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        let input = "a=1 b=2";
+        let r: Option<BorrowedValue> = kv
+            .run_with_deadline(input, Instant::now() + Duration::from_secs(10))
+            .expect("Failed to split input");
+        assert_eq!(r, Some(simd_json::json!({"a": "1", "b": "2"}).into()));
+        assert_eq!(
+            kv.run_with_deadline::<BorrowedValue>(input, Instant::now()),
+            Err(Error::Timeout)
+        );
+        assert_eq!(
+            kv.with_max_input_length(1)
+                .run_with_deadline::<BorrowedValue>(input, Instant::now()),
+            Err(Error::LimitExceeded(Limit::InputLength(1)))
+        );
+    }
+
+    #[test]
+    fn empty_values() {
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_quotes(&['"']);
+        let input = r#"a= b="" c=1"#;
+        let r: BorrowedValue = kv.run(input).expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"a": "", "b": "", "c": "1"}));
+        let r: BorrowedValue = kv
+            .clone()
+            .with_empty_values(EmptyValuePolicy::Drop)
+            .run(input)
+            .expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"c": "1"}));
+        let r: BorrowedValue = kv
+            .clone()
+            .with_empty_values(EmptyValuePolicy::Null)
+            .run(input)
+            .expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"a": null, "b": null, "c": "1"}));
+        assert_eq!(
+            kv.with_empty_values(EmptyValuePolicy::Drop)
+                .run::<BorrowedValue>("a="),
+            None
+        );
+    }
+
+    #[test]
+    fn empty_keys() {
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        let input = "a=1 =2";
+        let r: BorrowedValue = kv.run(input).expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"a": "1", "": "2"}));
+        let r: BorrowedValue = kv
+            .clone()
+            .with_empty_keys(EmptyKeyPolicy::Drop)
+            .run(input)
+            .expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"a": "1"}));
+        let r: BorrowedValue = kv
+            .clone()
+            .with_empty_keys(EmptyKeyPolicy::Rename("_empty_key".to_string()))
+            .run(input)
+            .expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"a": "1", "_empty_key": "2"}));
+        let kv = kv.with_empty_keys(EmptyKeyPolicy::Reject);
+        assert_eq!(kv.run::<BorrowedValue>(input), None);
+        assert_eq!(
+            kv.run_checked::<BorrowedValue>(input),
+            Err(Error::EmptyKey(4))
+        );
+    }
+
+    #[test]
+    fn strict() {
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_strict(true);
+        let r: BorrowedValue = kv.run("a=1 b=2").expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"a": "1", "b": "2"}));
+        assert_eq!(kv.run::<BorrowedValue>("a=1 stray b=2"), None);
+        assert_eq!(
+            kv.run_checked::<BorrowedValue>("a=1 stray b=2"),
+            Err(Error::UnsplitField("stray".to_string(), 4))
+        );
+        assert_eq!(
+            kv.run_checked::<BorrowedValue>("a=1 b=2=3"),
+            Err(Error::UnsplitField("b=2=3".to_string(), 4))
+        );
+        let r: BorrowedValue = kv
+            .with_flag_policy(FlagPolicy::True)
+            .run("a=1 stray")
+            .expect("Failed to split input");
+        assert_eq!(r, simd_json::json!({"a": "1", "stray": true}));
+    }
+
+    #[test]
+    fn deadline_while_splitting() {
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_quotes(&['"']);
+        let input = "k=\"v\" ".repeat(16000);
+        let t_run = fastest(|| assert!(kv.run::<BorrowedValue>(&input).is_some()));
+        let t_deadline = fastest(|| {
+            assert_eq!(
+                kv.run_with_deadline::<BorrowedValue>(&input, Instant::now()),
+                Err(Error::Timeout)
+            );
+        });
+        assert!(
+            t_deadline * 2 < t_run,
+            "{t_deadline:?} with an expired deadline, {t_run:?} without"
+        );
+    }
+
+    #[test]
+    fn run_direct() {
+        let inputs = [
+            "",
+            " ",
+            "a=1",
+            "a=1 a=2 b=3",
+            "=",
+            "a==b c=d",
+            " a=1 ",
+            "a=1  b=2",
+            "x",
+            "4=2.5",
+        ];
+        let plain = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        let patterns = [
+            plain.clone(),
+            plain.clone().with_coerce_values(true),
+            plain.with_trim_first_field(true),
+        ];
+        for kv in patterns {
+            assert!(kv.direct_seperators().is_some());
+            for input in inputs {
+                assert_eq!(
+                    kv.run::<BorrowedValue>(input),
+                    kv.build::<BorrowedValue>(kv.extract(input)),
+                    "{input:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn find() {
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        let input = "a=1 b=2 a=3 c=x=y";
+        assert_eq!(kv.find(input, "a"), Some("1"));
+        assert_eq!(kv.find(input, "b"), Some("2"));
+        assert_eq!(kv.find(input, "c"), None);
+        assert!(kv.contains_key(input, "b"));
+        assert!(!kv.contains_key(input, "d"));
+        assert!(!kv.contains_key(" ", ""));
+
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_quotes(&['"'])
+            .with_positional_keys(&["level"]);
+        let input = r#"info "b"="x y" a=1"#;
+        assert_eq!(kv.find(input, "level"), Some("info"));
+        assert_eq!(kv.find(input, "b"), Some("x y"));
+        assert!(kv.contains_key(input, "a"));
+    }
+
+    #[test]
+    fn split_strategy() {
+        let kv =
+            Pattern::compile("%{key}=%{val}ab%{key}=%{val}ca").expect("Failed to build pattern");
+        // `ab` is split on first so the `ca` overlapping it never matches
+        let r: BorrowedValue = kv.run("x=1cab=2").expect("Failed to split input");
+        assert_eq!(r["x"], "1c");
+        assert_eq!(r[""], "2");
+
+        let kv = kv.with_split_strategy(SplitStrategy::LongestMatch);
+        let r: BorrowedValue = kv.run("x=1cab=2").expect("Failed to split input");
+        assert_eq!(r.as_object().map(Object::len).unwrap_or_default(), 2);
+        assert_eq!(r["x"], "1");
+        assert_eq!(r["b"], "2");
+
+        // otherwise both strategies agree
+        let input = "a=1abb=\"ca\"cac==ab";
+        for strategy in [SplitStrategy::Sequential, SplitStrategy::LongestMatch] {
+            let kv = kv
+                .clone()
+                .with_split_strategy(strategy)
+                .with_quotes(&['"'])
+                .with_collapse_consecutive_separators(true);
+            let r: BorrowedValue = kv.run(input).expect("Failed to split input");
+            assert_eq!(r.as_object().map(Object::len).unwrap_or_default(), 3);
+            assert_eq!(r["a"], "1");
+            assert_eq!(r["b"], "ca");
+            assert_eq!(r["c"], "");
+        }
+    }
+
+    #[test]
+    fn run_prefixed() {
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        let input = "http.status=200 http.method=GET db.pool=5 http=1";
+        let r: BorrowedValue = kv
+            .run_prefixed(input, "http.", false)
+            .expect("Failed to split input");
+        assert_eq!(r.as_object().map(Object::len).unwrap_or_default(), 2);
+        assert_eq!(r["http.status"], "200");
+        assert_eq!(r["http.method"], "GET");
+
+        let r: BorrowedValue = kv
+            .run_prefixed(input, "http.", true)
+            .expect("Failed to split input");
+        assert_eq!(r.as_object().map(Object::len).unwrap_or_default(), 2);
+        assert_eq!(r["status"], "200");
+        assert_eq!(r["method"], "GET");
+
+        let r: Option<BorrowedValue> = kv.run_prefixed(input, "tls.", true);
+        assert!(r.is_none());
     }
 
     #[test]
@@ -0,0 +1,113 @@
+// Copyright 2020-2021, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Parsing logfmt, e.g. `level=info msg="hello \"world\"" debug`
+
+use crate::{FlagPolicy, Pattern};
+
+impl Pattern {
+    /// A pattern for logfmt: pairs are seperated by spaces, keys and values
+    /// by `=`, values can be double quoted with `\"` escapes and bare keys
+    /// become `true`.
+    ///
+    /// Malformed fields are dropped like with any other pattern, use
+    /// `with_strict_logfmt` to reject them instead.
+    #[must_use]
+    pub fn logfmt() -> Self {
+        Self {
+            field_seperators: vec![" ".to_string()].into(),
+            key_seperators: vec!["=".to_string()].into(),
+            ..Self::default()
+        }
+        .with_quotes(&['"'])
+        .with_escape_char('\\')
+        .with_flag_policy(FlagPolicy::True)
+    }
+}
+
+/// If a field is a bare key, a `key=` or a `key=value` pair in logfmt
+/// syntax, values are either unquoted or entirely double quoted.
+pub(crate) fn well_formed(field: &str) -> bool {
+    let (key, val) = field.split_once('=').unwrap_or((field, ""));
+    !key.is_empty() && is_ident(key) && (is_ident(val) || is_quoted(val))
+}
+
+fn is_ident(s: &str) -> bool {
+    s.chars().all(|c| c > ' ' && c != '=' && c != '"')
+}
+
+/// If `s` is a double quoted string that ends with its closing quote
+fn is_quoted(s: &str) -> bool {
+    let Some(inner) = s.strip_prefix('"') else {
+        return false;
+    };
+    let mut escaped = false;
+    for (i, c) in inner.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == '"' {
+            return i + 1 == inner.len();
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{DropReason, ParseError};
+    use simd_json::OwnedValue;
+
+    #[test]
+    fn logfmt() {
+        let kv = Pattern::logfmt();
+        let r: OwnedValue = kv
+            .run(r#"level=info msg="hello \"world\" a=b" debug  path= x=1=2"#)
+            .expect("Failed to split input");
+        assert_eq!(
+            r,
+            simd_json::json!({
+                "level": "info",
+                "msg": "hello \"world\" a=b",
+                "debug": true,
+                "path": ""
+            })
+        );
+    }
+
+    #[test]
+    fn strict_logfmt() {
+        let kv = Pattern::logfmt().with_strict_logfmt(true);
+        let r: OwnedValue = kv
+            .run(r#"level=info msg="a b"  debug"#)
+            .expect("Failed to split input");
+        assert_eq!(
+            r,
+            simd_json::json!({"level": "info", "msg": "a b", "debug": true})
+        );
+        for input in [r#"a=1 msg="open"#, "a=1 x=1=2", "a=1 =2", r#"a="b"c"#] {
+            assert_eq!(kv.run::<OwnedValue>(input), None, "{input}");
+        }
+        match kv.try_run::<OwnedValue>("a=1 x=1=2") {
+            Err(ParseError::Field(e)) => {
+                assert_eq!(e.field(), "x=1=2");
+                assert_eq!(e.offset(), 4);
+                assert_eq!(e.reason(), DropReason::Malformed);
+            }
+            r => panic!("unexpected result {r:?}"),
+        }
+    }
+}
@@ -0,0 +1,87 @@
+// Copyright 2020-2021, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Unicode normalization of keys and values
+
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use unicode_normalization::{
+    is_nfc_quick, is_nfd_quick, is_nfkc_quick, is_nfkd_quick, IsNormalized, UnicodeNormalization,
+};
+
+/// A unicode normalization form
+#[derive(PartialEq, Debug, Clone, Copy, Hash, Serialize, Deserialize, Eq)]
+pub enum NormForm {
+    /// canonical decomposition followed by canonical composition
+    Nfc,
+    /// canonical decomposition
+    Nfd,
+    /// compatibility decomposition followed by canonical composition
+    Nfkc,
+    /// compatibility decomposition
+    Nfkd,
+}
+
+impl NormForm {
+    /// Normalizes a string, strings that are already normalized are returned
+    /// as they are
+    pub(crate) fn normalize(self, s: Cow<'_, str>) -> Cow<'_, str> {
+        let normalized = match self {
+            Self::Nfc => is_nfc_quick(s.chars()),
+            Self::Nfd => is_nfd_quick(s.chars()),
+            Self::Nfkc => is_nfkc_quick(s.chars()),
+            Self::Nfkd => is_nfkd_quick(s.chars()),
+        };
+        if normalized == IsNormalized::Yes {
+            return s;
+        }
+        Cow::Owned(match self {
+            Self::Nfc => s.nfc().collect(),
+            Self::Nfd => s.nfd().collect(),
+            Self::Nfkc => s.nfkc().collect(),
+            Self::Nfkd => s.nfkd().collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{DuplicatePolicy, Pattern};
+    use simd_json::borrowed::Object;
+    use simd_json::{prelude::*, BorrowedValue};
+
+    #[test]
+    fn normalized_keys_merge() {
+        let composed = "caf\u{e9}";
+        let decomposed = "cafe\u{301}";
+        let input = format!("{composed}=1 {decomposed}=2 n=\u{fb01}");
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_duplicates(DuplicatePolicy::Array);
+        let r: BorrowedValue = kv.run(&input).expect("Failed to split input");
+        assert_eq!(r.as_object().map(Object::len), Some(3));
+
+        let kv = kv.with_normalize(NormForm::Nfc);
+        let r: BorrowedValue = kv.run(&input).expect("Failed to split input");
+        assert_eq!(r.as_object().map(Object::len), Some(2));
+        assert_eq!(r[composed], BorrowedValue::from(vec!["1", "2"]));
+        assert_eq!(r["n"], "\u{fb01}");
+
+        let kv = kv.with_normalize(NormForm::Nfkd);
+        let r: BorrowedValue = kv.run(&input).expect("Failed to split input");
+        assert_eq!(r[decomposed], BorrowedValue::from(vec!["1", "2"]));
+        assert_eq!(r["n"], "fi");
+    }
+}
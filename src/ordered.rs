@@ -0,0 +1,56 @@
+// Copyright 2020-2021, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Output that keeps the keys in input order
+
+use crate::{Depth, Output, Pattern};
+use indexmap::IndexMap;
+use std::borrow::Cow;
+
+impl Pattern {
+    /// Splits a string that represents KV pairs like `run` into a map that
+    /// keeps the keys in the order they first appear in the input, values
+    /// nested below the top level are built as `V`.
+    pub fn run_ordered<'input, V>(
+        &self,
+        input: &'input str,
+    ) -> Option<IndexMap<Cow<'input, str>, V>>
+    where
+        V: Output<'input>,
+    {
+        self.obj(self.extract(input), Depth::top(self))
+            .ok()??
+            .into_index_map()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use simd_json::OwnedValue;
+
+    #[test]
+    fn run_ordered() {
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_coerce_values(true);
+        let r: IndexMap<_, OwnedValue> = kv
+            .run_ordered("zeta=1 alpha=2 mid=x zeta=3")
+            .expect("Failed to split input");
+        let keys: Vec<_> = r.keys().map(AsRef::as_ref).collect();
+        assert_eq!(keys, ["zeta", "alpha", "mid"]);
+        assert_eq!(r["zeta"], 3);
+        assert_eq!(kv.run_ordered::<OwnedValue>("nothing"), None);
+    }
+}
@@ -0,0 +1,118 @@
+// Copyright 2020-2021, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! (De)serializing patterns as their pattern string, for use with
+//! `#[serde(with = "tremor_kv::pattern_string")]`
+//!
+//! Deserializing compiles the string with `Pattern::compile`. Serializing
+//! fails for patterns with options that a pattern string can not express,
+//! e.g. quotes or coercion.
+
+use crate::Pattern;
+use serde::{de, ser, Deserialize, Deserializer, Serializer};
+use std::borrow::Cow;
+
+/// Serializes a pattern as its pattern string
+///
+/// # Errors
+/// if the pattern has options beyond its seperators
+pub fn serialize<S: Serializer>(pattern: &Pattern, serializer: S) -> Result<S::Ok, S::Error> {
+    match to_pattern_string(pattern) {
+        Ok(s) => serializer.serialize_str(&s),
+        Err(s) => Err(ser::Error::custom(format!(
+            "the pattern has options that can not be written as the pattern string '{s}'"
+        ))),
+    }
+}
+
+/// The pattern string of a pattern if it compiles back into the same
+/// pattern, otherwise the closest pattern string as error
+pub(crate) fn to_pattern_string(pattern: &Pattern) -> Result<String, String> {
+    let s = pattern.to_string();
+    if Pattern::compile(&s).as_ref() == Ok(pattern) {
+        Ok(s)
+    } else {
+        Err(s)
+    }
+}
+
+/// Deserializes a pattern from a pattern string
+///
+/// # Errors
+/// if the input is not a string or not a valid pattern
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Pattern, D::Error> {
+    let s = Cow::<str>::deserialize(deserializer)?;
+    Pattern::compile(&s).map_err(de::Error::custom)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Error, Pattern};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Codec {
+        #[serde(with = "crate::pattern_string")]
+        pattern: Pattern,
+    }
+
+    #[test]
+    fn pattern_string() {
+        let codec = Codec {
+            pattern: Pattern::compile("%{key}=%{val}&").expect("Failed to build pattern"),
+        };
+        let mut json = simd_json::to_vec(&codec).expect("Failed to serialize");
+        assert_eq!(json, br#"{"pattern":"%{key}=%{val}&"}"#);
+        let back: Codec = simd_json::from_slice(&mut json).expect("Failed to deserialize");
+        assert_eq!(back, codec);
+
+        let mut json = br#"{"pattern":"%{key}=%{val}="}"#.to_vec();
+        assert!(simd_json::from_slice::<Codec>(&mut json).is_err());
+
+        let codec = Codec {
+            pattern: codec.pattern.with_quotes(&['"']),
+        };
+        assert!(simd_json::to_vec(&codec).is_err());
+    }
+
+    #[test]
+    fn validated() {
+        let mut json = br#""%{key}=%{val}&""#.to_vec();
+        let kv: Pattern = simd_json::from_slice(&mut json).expect("Failed to deserialize");
+        assert_eq!(Pattern::compile("%{key}=%{val}&"), Ok(kv));
+
+        let kv = Pattern::compile("%{key}=%{val};%{key}:%{val}")
+            .expect("Failed to build pattern")
+            .with_coerce_values(true);
+        let mut json = simd_json::to_vec(&kv).expect("Failed to serialize");
+        assert!(json.starts_with(b"{"));
+        let back: Pattern = simd_json::from_slice(&mut json).expect("Failed to deserialize");
+        assert_eq!(back, kv);
+
+        let kv = Pattern::compile("%{key}=%{val}&").expect("Failed to build pattern");
+        let mut json = simd_json::to_vec(&kv).expect("Failed to serialize");
+        assert_eq!(json, br#""%{key}=%{val}&""#);
+        let back: Pattern = simd_json::from_slice(&mut json).expect("Failed to deserialize");
+        assert_eq!(back, kv);
+
+        let mut json = br#"{"field_seperators": ["=", ";"], "key_seperators": ["="]}"#.to_vec();
+        let e = simd_json::from_slice::<Pattern>(&mut json).expect_err("Overlapping seperators");
+        assert!(e
+            .to_string()
+            .contains(&Error::DoubleSeperator("=".to_string()).to_string()));
+        let mut json = br#"{"field_seperators": [";", " "], "key_seperators": ["="]}"#.to_vec();
+        let kv: Pattern = simd_json::from_slice(&mut json).expect("Failed to deserialize");
+        assert_eq!(kv.field_separators(), [" ", ";"]);
+    }
+}
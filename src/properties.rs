@@ -0,0 +1,224 @@
+// Copyright 2020-2021, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Parsing Java properties and `.env` style files with comments and line
+//! continuations
+
+use crate::{Output, Pair, Pattern, Trim};
+use std::borrow::Cow;
+
+impl Pattern {
+    /// A pattern for Java properties and `.env` files: one pair per line
+    /// with `=` or `:` between key and value, to be used with
+    /// `run_properties`.
+    #[must_use]
+    pub fn properties() -> Self {
+        Self {
+            field_seperators: vec!["\n".to_string()].into(),
+            key_seperators: vec![":".to_string(), "=".to_string()].into(),
+            ..Self::default()
+        }
+        .with_split_once(true)
+        .with_trim_first_field(true)
+        .with_trim_key(Trim::Whitespace)
+        .with_trim_value(Trim::Whitespace)
+    }
+
+    /// Splits a properties file into pairs line by line: blank lines and
+    /// lines starting with `#` or `!` are skipped, a line ending in an
+    /// unescaped `\` continues on the next line and keys and values are
+    /// split at the first unescaped key seperator. Escapes like `\t`,
+    /// `\=` and `\uXXXX` are decoded. Lines without a key seperator are
+    /// keys with an empty value.
+    pub fn run_properties<'input, V>(&self, input: &'input str) -> Option<V>
+    where
+        V: Output<'input>,
+    {
+        let pairs = logical_lines(input)
+            .into_iter()
+            .map(|line| {
+                let (key, val) = self.split_property(line);
+                Pair {
+                    key: unescape(key),
+                    val: unescape(val),
+                    nested: false,
+                    flag: false,
+                }
+            })
+            .collect();
+        self.build(pairs)
+    }
+
+    /// Splits a line at the first key seperator that is not escaped
+    fn split_property<'l>(&self, line: Cow<'l, str>) -> (Cow<'l, str>, Cow<'l, str>) {
+        let mut escaped = false;
+        let mut at = None;
+        for (i, c) in line.char_indices() {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if let Some(ks) = self
+                .key_seperators
+                .iter()
+                .find(|ks| !ks.is_empty() && line[i..].starts_with(ks.as_str()))
+            {
+                at = Some((i, i + ks.len()));
+                break;
+            }
+        }
+        let (start, end) = at.unwrap_or((line.len(), line.len()));
+        match line {
+            Cow::Borrowed(l) => (
+                Cow::Borrowed(l[..start].trim_end()),
+                Cow::Borrowed(l[end..].trim_start()),
+            ),
+            Cow::Owned(l) => (
+                Cow::Owned(l[..start].trim_end().to_string()),
+                Cow::Owned(l[end..].trim_start().to_string()),
+            ),
+        }
+    }
+}
+
+/// Joins continued lines and drops blank and comment lines, leading
+/// whitespace is removed from every line.
+fn logical_lines(input: &str) -> Vec<Cow<'_, str>> {
+    let mut lines = Vec::new();
+    let mut continued: Option<String> = None;
+    for line in input.lines() {
+        let line = line.trim_start();
+        if continued.is_none() && (line.is_empty() || line.starts_with(['#', '!'])) {
+            continue;
+        }
+        if line.bytes().rev().take_while(|b| *b == b'\\').count() % 2 == 1 {
+            continued
+                .get_or_insert_with(String::new)
+                .push_str(&line[..line.len() - 1]);
+            continue;
+        }
+        match continued.take() {
+            Some(mut l) => {
+                l.push_str(line);
+                lines.push(Cow::Owned(l));
+            }
+            None => lines.push(Cow::Borrowed(line)),
+        }
+    }
+    lines.extend(continued.map(Cow::Owned));
+    lines
+}
+
+/// Decodes `\t`, `\n`, `\r`, `\f` and `\uXXXX` escapes, any other escaped
+/// character stands for itself.
+fn unescape(s: Cow<'_, str>) -> Cow<'_, str> {
+    if !s.contains('\\') {
+        return s;
+    }
+    let mut out = String::with_capacity(s.len());
+    let mut cs = s.chars();
+    while let Some(c) = cs.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match cs.next() {
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('f') => out.push('\u{c}'),
+            Some('u') => {
+                let unit = hex4(&mut cs);
+                let c = match unit {
+                    Some(high @ 0xd800..=0xdbff) => {
+                        let rest = cs.as_str();
+                        match rest.strip_prefix("\\u").and_then(|r| {
+                            let mut r = r.chars();
+                            hex4(&mut r).map(|low| (low, r))
+                        }) {
+                            Some((low @ 0xdc00..=0xdfff, r)) => {
+                                cs = r;
+                                char::from_u32(0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00))
+                            }
+                            _ => None,
+                        }
+                    }
+                    Some(unit) => char::from_u32(unit),
+                    None => None,
+                };
+                out.push(c.unwrap_or(char::REPLACEMENT_CHARACTER));
+            }
+            Some(c) => out.push(c),
+            None => (),
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// Reads four hex digits, nothing is consumed if there are none
+fn hex4(cs: &mut std::str::Chars<'_>) -> Option<u32> {
+    let mut digits = cs.clone();
+    let mut unit = 0;
+    for _ in 0..4 {
+        unit = unit * 16 + digits.next()?.to_digit(16)?;
+    }
+    *cs = digits;
+    Some(unit)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use simd_json::OwnedValue;
+
+    #[test]
+    fn run_properties() {
+        let input = "# a comment\n\
+                     ! another one\n\
+                     \n\
+                     db.host = localhost\n\
+                     db.port:5432\n\
+                     path=/usr/\\\n    local\\\n    /bin\n\
+                     greeting = caf\\u00e9 \\ud83d\\ude00\\tok\n\
+                     a\\=b = c=d\n\
+                     empty\n\
+                     trailing = x\\";
+        let kv = Pattern::properties().with_coerce_values(true);
+        let r: OwnedValue = kv.run_properties(input).expect("Failed to split input");
+        assert_eq!(
+            r,
+            simd_json::json!({
+                "db.host": "localhost",
+                "db.port": 5432,
+                "path": "/usr/local/bin",
+                "greeting": "café 😀\tok",
+                "a=b": "c=d",
+                "empty": "",
+                "trailing": "x"
+            })
+        );
+        assert_eq!(kv.run_properties::<OwnedValue>("# nothing\n"), None);
+        let r: OwnedValue = kv
+            .run_properties("a=\\uzz12\nb=\\u12\nc=\\ud83d\\uzz12")
+            .expect("Failed to split input");
+        assert_eq!(
+            r,
+            simd_json::json!({
+                "a": "\u{fffd}zz12",
+                "b": "\u{fffd}12",
+                "c": "\u{fffd}\u{fffd}zz12"
+            })
+        );
+    }
+}
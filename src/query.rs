@@ -0,0 +1,99 @@
+// Copyright 2020-2021, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Parsing URL query strings and form encoded bodies, e.g.
+//! `a=hello%20world&b=1`
+
+use crate::Pattern;
+use std::borrow::Cow;
+
+impl Pattern {
+    /// A pattern for URL query strings and `application/x-www-form-urlencoded`
+    /// bodies: pairs are seperated by `&`, keys and values by the first `=`
+    /// and both are percent decoded with `+` as space.
+    #[must_use]
+    pub fn query_string() -> Self {
+        Self {
+            field_seperators: vec!["&".to_string()].into(),
+            key_seperators: vec!["=".to_string()].into(),
+            ..Self::default()
+        }
+        .with_split_once(true)
+        .with_percent_decode(true)
+    }
+}
+
+/// Decodes `%XX` escapes and `+` as space, malformed escapes are kept as
+/// they are and invalid UTF-8 is replaced.
+pub(crate) fn percent_decode(s: Cow<'_, str>) -> Cow<'_, str> {
+    if !s.contains(['%', '+']) {
+        return s;
+    }
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' => {
+                // `from_str_radix` takes a sign so the digits are checked first
+                let hex = bytes
+                    .get(i + 1..i + 3)
+                    .filter(|h| h.iter().all(u8::is_ascii_hexdigit))
+                    .and_then(|h| {
+                        std::str::from_utf8(h)
+                            .ok()
+                            .and_then(|h| u8::from_str_radix(h, 16).ok())
+                    });
+                if let Some(b) = hex {
+                    out.push(b);
+                    i += 2;
+                } else {
+                    out.push(b'%');
+                }
+            }
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    Cow::Owned(String::from_utf8_lossy(&out).into_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use simd_json::OwnedValue;
+
+    #[test]
+    fn query_string() {
+        let kv = Pattern::query_string();
+        let r: OwnedValue = kv
+            .run("a=hello%20world&b=1&q=x+y%3Dz&r=a=b&s=%zz%&n%C3%A4me=%E2%9C%93")
+            .expect("Failed to split input");
+        assert_eq!(
+            r,
+            simd_json::json!({
+                "a": "hello world",
+                "b": "1",
+                "q": "x y=z",
+                "r": "a=b",
+                "s": "%zz%",
+                "näme": "✓"
+            })
+        );
+        assert_eq!(percent_decode(Cow::Borrowed("%ff")), "\u{fffd}");
+        assert_eq!(percent_decode(Cow::Borrowed("%+A")), "% A");
+        assert_eq!(percent_decode(Cow::Borrowed("%-1")), "%-1");
+    }
+}
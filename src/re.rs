@@ -0,0 +1,258 @@
+// Copyright 2020-2021, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Regular expression seperators
+
+use crate::{handle_escapes, Error, Pattern, Seperators};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::hash::{Hash, Hasher};
+
+/// The default limit of the compiled size of seperator regular expressions
+pub const DEFAULT_REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+/// A seperator given as a regular expression, it is compared, hashed and
+/// serialized by its source
+#[derive(Debug, Clone)]
+pub(crate) struct RegexSeperator(pub(crate) Regex);
+
+impl RegexSeperator {
+    pub(crate) fn compile(pattern: &str, size_limit: usize) -> Result<Self, Error> {
+        RegexBuilder::new(pattern)
+            .size_limit(size_limit)
+            .build()
+            .map(Self)
+            .map_err(|e| match e {
+                regex::Error::CompiledTooBig(limit) => {
+                    Error::RegexTooComplex(pattern.to_string(), limit)
+                }
+                e => Error::InvalidRegex(pattern.to_string(), e.to_string()),
+            })
+    }
+}
+
+impl PartialEq for RegexSeperator {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+
+impl Eq for RegexSeperator {}
+
+impl Hash for RegexSeperator {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.as_str().hash(state);
+    }
+}
+
+impl Serialize for RegexSeperator {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.0.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for RegexSeperator {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        Self::compile(&pattern, DEFAULT_REGEX_SIZE_LIMIT).map_err(serde::de::Error::custom)
+    }
+}
+
+impl Pattern {
+    /// compiles a pattern that splits fields and keys at matches of
+    /// regular expressions, e.g. `\s*,\s*` and `\s*=\s*`
+    ///
+    /// The compiled size of each expression is limited to
+    /// `DEFAULT_REGEX_SIZE_LIMIT`, use `compile_regex_with_limit` for
+    /// a different limit.
+    ///
+    /// # Errors
+    /// fails if either expression is invalid or too complex
+    pub fn compile_regex(field_pattern: &str, key_pattern: &str) -> Result<Self, Error> {
+        Self::compile_regex_with_limit(field_pattern, key_pattern, DEFAULT_REGEX_SIZE_LIMIT)
+    }
+
+    /// compiles a pattern like `compile_regex` with a limit of the compiled
+    /// size of each expression in bytes, lower this for patterns that come
+    /// from untrusted input.
+    ///
+    /// # Errors
+    /// fails if either expression is invalid or its compiled size exceeds
+    /// the limit
+    pub fn compile_regex_with_limit(
+        field_pattern: &str,
+        key_pattern: &str,
+        size_limit: usize,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            field_seperators: Seperators::default(),
+            key_seperators: Seperators::default(),
+            field_regex: Some(RegexSeperator::compile(field_pattern, size_limit)?),
+            key_regex: Some(RegexSeperator::compile(key_pattern, size_limit)?),
+            ..Self::default()
+        })
+    }
+
+    /// compiles a pattern of a single `%{key}<seperator>%{val}<seperator>`
+    /// group where either seperator can be a `/re:<expression>/`, the key
+    /// seperator has to be given and when `strict` the field seperator too
+    pub(crate) fn compile_regex_syntax(pattern: &str, strict: bool) -> Result<Self, Error> {
+        let rest = pattern
+            .strip_prefix("%{key}")
+            .ok_or(Error::InvalidPattern(0))?;
+        let (key, rest) = if let Some(re) = rest.strip_prefix("/re:") {
+            let end = re.find("/%{val}").ok_or(Error::InvalidPattern(6))?;
+            (Err(&re[..end]), &re[end + 1..])
+        } else {
+            let end = rest.find("%{val}").ok_or(Error::InvalidPattern(6))?;
+            if end == 0 {
+                return Err(Error::MissingKeySeperator(6));
+            }
+            (Ok(handle_escapes(&rest[..end])?), &rest[end..])
+        };
+        let offset = pattern.len() - rest.len();
+        let field = rest
+            .strip_prefix("%{val}")
+            .ok_or(Error::InvalidPattern(offset))?;
+        if let Some(i) = field.find("%{") {
+            return Err(Error::InvalidPattern(offset + 6 + i));
+        }
+        let field = match field
+            .strip_prefix("/re:")
+            .and_then(|re| re.strip_suffix('/'))
+        {
+            Some(re) => Err(re),
+            None => Ok(handle_escapes(field)?),
+        };
+        if strict && field.as_ref().is_ok_and(String::is_empty) {
+            return Err(Error::NoFieldSeperator(pattern.to_string()));
+        }
+        let literals = |sep: &Result<String, &str>| match sep {
+            Ok(sep) if !sep.is_empty() => vec![sep.clone()],
+            _ => Vec::new(),
+        };
+        let mut p = Self::from_seperators(literals(&field), literals(&key))?;
+        if let Err(re) = key {
+            p.key_seperators = Seperators::default();
+            p.key_regex = Some(RegexSeperator::compile(re, DEFAULT_REGEX_SIZE_LIMIT)?);
+        }
+        if let Err(re) = field {
+            p.field_seperators = Seperators::default();
+            p.field_regex = Some(RegexSeperator::compile(re, DEFAULT_REGEX_SIZE_LIMIT)?);
+        }
+        Ok(p)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use simd_json::prelude::*;
+    use simd_json::BorrowedValue;
+
+    #[test]
+    fn compile_regex() {
+        let kv = Pattern::compile_regex(r"\s*[,;]\s*", r"\s*[=:]\s*")
+            .expect("Failed to build pattern")
+            .with_quotes(&['"']);
+        let r: BorrowedValue = kv
+            .run("a = 1 ,b:2;  c=\"x; y\" ; bare")
+            .expect("Failed to split input");
+        assert_eq!(r["a"], "1");
+        assert_eq!(r["b"], "2");
+        assert_eq!(r["c"], "x; y");
+        assert!(r.get("bare").is_none());
+        assert!(kv.matches("a=1 , b=2"));
+        assert_eq!(kv.annotate_input("a = 1, b=2"), "a[ = ]1|, |b[=]2");
+
+        let same = Pattern::compile_regex(r"\s*[,;]\s*", r"\s*[=:]\s*")
+            .expect("Failed to build pattern")
+            .with_quotes(&['"']);
+        assert_eq!(kv, same);
+        assert_eq!(kv.fingerprint(), same.fingerprint());
+
+        assert!(matches!(
+            Pattern::compile_regex("(", "="),
+            Err(Error::InvalidRegex(p, _)) if p == "("
+        ));
+    }
+
+    #[test]
+    fn regex_syntax() {
+        let kv =
+            Pattern::compile(r"%{key}/re:\s*=\s*/%{val}/re:\s+/").expect("Failed to build pattern");
+        assert_eq!(
+            kv,
+            Pattern::compile_regex(r"\s+", r"\s*=\s*").expect("Failed to build pattern")
+        );
+        assert_eq!(kv.to_string(), r"%{key}/re:\s*=\s*/%{val}/re:\s+/");
+        let r: BorrowedValue = kv.run("a=1 \t b=2").expect("Failed to split input");
+        assert_eq!(r["a"], "1");
+        assert_eq!(r["b"], "2");
+
+        let kv = Pattern::compile_strict(r"%{key}/re:=/%{val}/re:\s+/")
+            .expect("Failed to build pattern");
+        assert_eq!(
+            kv,
+            Pattern::compile_regex(r"\s+", "=").expect("Failed to build pattern")
+        );
+        assert_eq!(
+            Pattern::compile_strict(r"%{key}/re:=/%{val}"),
+            Err(Error::NoFieldSeperator(r"%{key}/re:=/%{val}".to_string()))
+        );
+        assert_eq!(
+            Pattern::compile_strict(r"%{key}%{val}/re:\s+/"),
+            Err(Error::MissingKeySeperator(6))
+        );
+        assert_eq!(
+            Pattern::compile(r"%{key}%{val}/re:\s+/"),
+            Err(Error::MissingKeySeperator(6))
+        );
+        assert!(Pattern::compile_strict(r"%{key}=%{val}/re:\s+/").is_ok());
+
+        let kv =
+            Pattern::compile(r"%{key}=%{val}/re:\s*[,;]\s*/").expect("Failed to build pattern");
+        let r: BorrowedValue = kv.run("a=1 , b=2;c=3").expect("Failed to split input");
+        assert_eq!(r["b"], "2");
+        assert_eq!(r["c"], "3");
+
+        let kv = Pattern::compile(r"%{key}/re:[=:]/%{val}").expect("Failed to build pattern");
+        let r: BorrowedValue = kv.run("a=1 b:2").expect("Failed to split input");
+        assert_eq!(r["a"], "1");
+        assert_eq!(r["b"], "2");
+
+        assert!(matches!(
+            Pattern::compile("%{key}/re:(/%{val}"),
+            Err(Error::InvalidRegex(p, _)) if p == "("
+        ));
+        assert_eq!(
+            Pattern::compile(r"%{key}/re:=/%{val} %{key}:%{val}"),
+            Err(Error::InvalidPattern(19))
+        );
+    }
+
+    #[test]
+    fn regex_size_limit() {
+        let huge = format!("(?:{}){{100}}", r"\w{1,64}");
+        assert!(matches!(
+            Pattern::compile_regex(&huge, "="),
+            Err(Error::RegexTooComplex(p, _)) if p == huge
+        ));
+        assert!(Pattern::compile_regex_with_limit(r"\s+", "=", 1 << 16).is_ok());
+        assert!(matches!(
+            Pattern::compile_regex_with_limit(r"\s+", r"\w{1,64}", 64),
+            Err(Error::RegexTooComplex(_, 64))
+        ));
+    }
+}
@@ -0,0 +1,361 @@
+// Copyright 2020-2021, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reporting on what happened to the fields of an input
+
+use crate::value::InvalidValue;
+use crate::{Depth, Output, Pattern};
+use std::fmt;
+
+/// Why a field of the input was dropped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DropReason {
+    /// the field has no key seperator
+    NoKeySeperator,
+    /// the field has more than one key seperator
+    TooManyKeySeperators,
+    /// the field has a new key after the distinct key limit was reached
+    DistinctKeyLimit,
+    /// the field is not well formed logfmt with `with_strict_logfmt`
+    Malformed,
+    /// the input is longer than the maximum input length, the field is the
+    /// whole input
+    InputTooLong,
+    /// the field is the first one after the maximum number of fields
+    TooManyFields,
+    /// the key of the field is longer than the maximum key length
+    KeyTooLong,
+    /// the value of the field is longer than the maximum value length
+    ValueTooLong,
+    /// the deadline passed before the field was split, the field is the
+    /// whole input if it passed while splitting it into fields
+    Timeout,
+    /// the key of the field is empty and dropped or rejected
+    EmptyKey,
+}
+
+impl DropReason {
+    const ALL: [Self; 10] = [
+        Self::NoKeySeperator,
+        Self::TooManyKeySeperators,
+        Self::DistinctKeyLimit,
+        Self::Malformed,
+        Self::InputTooLong,
+        Self::TooManyFields,
+        Self::KeyTooLong,
+        Self::ValueTooLong,
+        Self::Timeout,
+        Self::EmptyKey,
+    ];
+}
+
+impl fmt::Display for DropReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoKeySeperator => write!(f, "no key seperator"),
+            Self::TooManyKeySeperators => write!(f, "more than one key seperator"),
+            Self::DistinctKeyLimit => write!(f, "distinct key limit reached"),
+            Self::Malformed => write!(f, "malformed logfmt"),
+            Self::InputTooLong => write!(f, "input too long"),
+            Self::TooManyFields => write!(f, "too many fields"),
+            Self::KeyTooLong => write!(f, "key too long"),
+            Self::ValueTooLong => write!(f, "value too long"),
+            Self::Timeout => write!(f, "deadline passed"),
+            Self::EmptyKey => write!(f, "empty key"),
+        }
+    }
+}
+
+/// Counts of the pairs extracted from an input and the fields dropped
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Report {
+    pairs: usize,
+    dropped: [usize; DropReason::ALL.len()],
+}
+
+impl Report {
+    /// The number of pairs extracted
+    pub fn pairs(&self) -> usize {
+        self.pairs
+    }
+
+    /// The number of fields dropped for the given reason
+    pub fn dropped(&self, reason: DropReason) -> usize {
+        self.dropped[reason as usize]
+    }
+
+    /// The number of fields dropped for any reason
+    pub fn total_dropped(&self) -> usize {
+        self.dropped.iter().sum()
+    }
+
+    /// The drop counts by reason, reasons that never occurred are skipped
+    pub fn drops(&self) -> impl Iterator<Item = (DropReason, usize)> + '_ {
+        DropReason::ALL
+            .into_iter()
+            .map(|r| (r, self.dropped(r)))
+            .filter(|(_, c)| *c > 0)
+    }
+
+    /// Adds the counts of another report, e.g. to aggregate over many
+    /// inputs
+    pub fn merge(&mut self, other: &Self) {
+        self.pairs += other.pairs;
+        for (d, o) in self.dropped.iter_mut().zip(other.dropped) {
+            *d += o;
+        }
+    }
+}
+
+/// A field of the input that does not form a pair
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldError<'input> {
+    offset: usize,
+    field: &'input str,
+    reason: DropReason,
+}
+
+impl<'input> FieldError<'input> {
+    /// The byte offset of the field in the input
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The text of the field
+    pub fn field(&self) -> &'input str {
+        self.field
+    }
+
+    /// Why the field does not form a pair
+    pub fn reason(&self) -> DropReason {
+        self.reason
+    }
+}
+
+impl fmt::Display for FieldError<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} in '{}' at byte {}",
+            self.reason, self.field, self.offset
+        )
+    }
+}
+
+/// Why no output could be built from an input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseError<'input> {
+    /// the input is empty or consists of whitespace only
+    Empty,
+    /// no pairs could be extracted, the field is the first field that does
+    /// not form a pair
+    Field(FieldError<'input>),
+    /// no pairs could be extracted although all fields are well formed,
+    /// e.g. as they are empty
+    NoPairs,
+    /// a value does not match its expected type with
+    /// `CoercionFallback::Error` or an array has gaps with
+    /// `IndexGapPolicy::Error`
+    InvalidValue,
+    /// values are nested deeper than the maximum total depth
+    MaxDepthExceeded(usize),
+}
+
+impl fmt::Display for ParseError<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "empty input"),
+            Self::Field(e) => write!(f, "no pairs, {e}"),
+            Self::NoPairs => write!(f, "no pairs"),
+            Self::InvalidValue => write!(f, "invalid value"),
+            Self::MaxDepthExceeded(d) => {
+                write!(
+                    f,
+                    "values are nested deeper than the maximum total depth of {d}"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError<'_> {}
+
+impl Pattern {
+    /// Splits a string that represents KV pairs like `run` and reports why
+    /// no output could be built if `run` would return `None`.
+    ///
+    /// # Errors
+    /// why no output could be built from the input
+    pub fn try_run<'input, V>(&self, input: &'input str) -> Result<V, ParseError<'input>>
+    where
+        V: Output<'input>,
+    {
+        if input.trim().is_empty() {
+            return Err(ParseError::Empty);
+        }
+        let mut first = None;
+        let pairs = self.extract_with(input, |reason, field| {
+            first.get_or_insert(FieldError {
+                offset: field.as_ptr() as usize - input.as_ptr() as usize,
+                field,
+                reason,
+            });
+        });
+        match self.obj(pairs, Depth::top(self)) {
+            Ok(Some(obj)) => obj.into_value().ok_or(ParseError::InvalidValue),
+            Ok(None) => Err(first.map_or(ParseError::NoPairs, ParseError::Field)),
+            Err(InvalidValue::Type) => Err(ParseError::InvalidValue),
+            Err(InvalidValue::Depth) => Err(ParseError::MaxDepthExceeded(self.depth_limit())),
+        }
+    }
+
+    /// Splits a string that represents KV pairs like `run` and reports how
+    /// many fields were dropped and why.
+    pub fn run_with_report<'input, V>(&self, input: &'input str) -> (Option<V>, Report)
+    where
+        V: Output<'input>,
+    {
+        let mut report = Report::default();
+        let pairs = self.extract_with(input, |reason, _| report.dropped[reason as usize] += 1);
+        report.pairs = pairs.len();
+        (self.build(pairs), report)
+    }
+
+    /// Splits a string that represents KV pairs like `run` but fails if
+    /// any field does not form a pair, all such fields are returned in
+    /// input order. Fields dropped because of the distinct key limit are
+    /// not errors.
+    ///
+    /// # Errors
+    /// the malformed fields, this is empty if there are none but no output
+    /// could be built, e.g. for empty input or values that do not match
+    /// their expected type with `CoercionFallback::Error`
+    pub fn run_strict_all<'input, V>(
+        &self,
+        input: &'input str,
+    ) -> Result<V, Vec<FieldError<'input>>>
+    where
+        V: Output<'input>,
+    {
+        let mut errors = Vec::new();
+        let pairs = self.extract_with(input, |reason, field| {
+            if reason != DropReason::DistinctKeyLimit {
+                errors.push(FieldError {
+                    offset: field.as_ptr() as usize - input.as_ptr() as usize,
+                    field,
+                    reason,
+                });
+            }
+        });
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        self.build(pairs).ok_or(errors)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{CoercionFallback, ExpectedType};
+    use simd_json::BorrowedValue;
+
+    #[test]
+    fn drop_reasons() {
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_max_distinct_keys(2);
+        let (r, report): (Option<BorrowedValue>, _) =
+            kv.run_with_report("a=1 stray b=2 c=3=4 bare d=5 a=6 e=");
+        let r = r.expect("Failed to split input");
+        assert_eq!(r["a"], "6");
+        assert_eq!(r["b"], "2");
+        assert_eq!(report.pairs(), 3);
+        assert_eq!(report.dropped(DropReason::NoKeySeperator), 2);
+        assert_eq!(report.dropped(DropReason::TooManyKeySeperators), 1);
+        assert_eq!(report.dropped(DropReason::DistinctKeyLimit), 2);
+        assert_eq!(report.total_dropped(), 5);
+        assert_eq!(report.drops().count(), 3);
+
+        let (r, other): (Option<BorrowedValue>, _) = kv.run_with_report("nothing");
+        assert!(r.is_none());
+        let mut total = report;
+        total.merge(&other);
+        assert_eq!(total.dropped(DropReason::NoKeySeperator), 3);
+        assert_eq!(total.pairs(), 3);
+    }
+
+    #[test]
+    fn run_strict_all() {
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        let r: BorrowedValue = kv.run_strict_all("a=1 b=2").expect("Failed to split input");
+        assert_eq!(r["a"], "1");
+
+        let errors = kv
+            .run_strict_all::<BorrowedValue>("a=1 stray b=2 c=3=4")
+            .expect_err("Input should be malformed");
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].offset(), 4);
+        assert_eq!(errors[0].field(), "stray");
+        assert_eq!(errors[0].reason(), DropReason::NoKeySeperator);
+        assert_eq!(errors[1].offset(), 14);
+        assert_eq!(errors[1].field(), "c=3=4");
+        assert_eq!(errors[1].reason(), DropReason::TooManyKeySeperators);
+        assert_eq!(
+            errors[1].to_string(),
+            "more than one key seperator in 'c=3=4' at byte 14"
+        );
+
+        assert_eq!(kv.run_strict_all::<BorrowedValue>(""), Err(Vec::new()));
+    }
+
+    #[test]
+    fn try_run() {
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_expected_type("port", ExpectedType::Integer)
+            .with_coercion_fallback(CoercionFallback::Error);
+        let r: BorrowedValue = kv.try_run("a=1 stray").expect("Failed to split input");
+        assert_eq!(r["a"], "1");
+
+        assert_eq!(kv.try_run::<BorrowedValue>(" "), Err(ParseError::Empty));
+        let e = kv
+            .try_run::<BorrowedValue>("hello a=b=c")
+            .expect_err("Input should be malformed");
+        assert_eq!(
+            e,
+            ParseError::Field(FieldError {
+                offset: 0,
+                field: "hello",
+                reason: DropReason::NoKeySeperator
+            })
+        );
+        assert_eq!(
+            e.to_string(),
+            "no pairs, no key seperator in 'hello' at byte 0"
+        );
+        assert_eq!(
+            kv.try_run::<BorrowedValue>("port=x"),
+            Err(ParseError::InvalidValue)
+        );
+
+        let kv = Pattern::compile("%{key}=%{val},").expect("Failed to build pattern");
+        assert_eq!(kv.try_run::<BorrowedValue>(",,"), Err(ParseError::NoPairs));
+        let kv = kv.with_nested_values(true).with_max_total_depth(0);
+        assert_eq!(
+            kv.try_run::<BorrowedValue>("a=b=c"),
+            Err(ParseError::MaxDepthExceeded(0))
+        );
+    }
+}
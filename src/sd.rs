@@ -0,0 +1,164 @@
+// Copyright 2020-2021, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Parsing syslog (RFC 5424) STRUCTURED-DATA like
+//! `[exampleSDID@32473 iut="3" eventSource="App"]` into an object with an
+//! object of parameters per SD-ID.
+
+use crate::value::{Obj, Val};
+use crate::{DuplicatePolicy, Output};
+use std::borrow::Cow;
+
+/// Parses STRUCTURED-DATA, `-` (the NILVALUE) is an empty object.
+///
+/// Repeated parameters of an element become an array, a repeated SD-ID
+/// replaces the earlier element. Returns `None` if the input is not well
+/// formed STRUCTURED-DATA.
+pub fn parse<'input, V>(input: &'input str) -> Option<V>
+where
+    V: Output<'input>,
+{
+    match parse_prefix(input)? {
+        (v, "") => Some(v),
+        _ => None,
+    }
+}
+
+/// Parses the STRUCTURED-DATA at the start of the input like `parse` and
+/// returns the rest after the space that follows it, e.g. the MSG of a
+/// syslog message.
+pub fn parse_prefix<'input, V>(input: &'input str) -> Option<(V, &'input str)>
+where
+    V: Output<'input>,
+{
+    let mut obj = Obj::with_capacity(1);
+    let rest = if let Some(rest) = input.strip_prefix('-') {
+        rest
+    } else {
+        let mut rest = element(input, &mut obj)?;
+        while rest.starts_with('[') {
+            rest = element(rest, &mut obj)?;
+        }
+        rest
+    };
+    let rest = match rest.strip_prefix(' ') {
+        Some(rest) => rest,
+        None if rest.is_empty() => rest,
+        None => return None,
+    };
+    Some((obj.into_value()?, rest))
+}
+
+/// Parses an SD-ELEMENT into `obj` and returns the rest of the input
+fn element<'input>(input: &'input str, obj: &mut Obj<'input>) -> Option<&'input str> {
+    let (id, mut rest) = name(input.strip_prefix('[')?)?;
+    let mut params = Obj::with_capacity(4);
+    loop {
+        if let Some(rest) = rest.strip_prefix(']') {
+            obj.insert(
+                Cow::Borrowed(id),
+                Val::Object(params),
+                DuplicatePolicy::Overwrite,
+            );
+            return Some(rest);
+        }
+        let (key, r) = name(rest.strip_prefix(' ')?)?;
+        let (val, r) = value(r.strip_prefix("=\"")?)?;
+        params.insert(Cow::Borrowed(key), Val::Str(val), DuplicatePolicy::Array);
+        rest = r;
+    }
+}
+
+/// Splits an SD-NAME off the input, 1 to 32 printable ASCII characters
+/// other than `=`, space, `]` and `"`
+fn name(input: &str) -> Option<(&str, &str)> {
+    let len = input
+        .bytes()
+        .take_while(|b| matches!(b, b'!'..=b'~') && !matches!(b, b'=' | b']' | b'"'))
+        .count();
+    (1..=32).contains(&len).then(|| input.split_at(len))
+}
+
+/// Splits a PARAM-VALUE up to its closing quote off the input, `\"`, `\\`
+/// and `\]` are unescaped, other backslashes are kept.
+fn value(input: &str) -> Option<(Cow<'_, str>, &str)> {
+    let mut owned: Option<String> = None;
+    let mut start = 0;
+    let mut bytes = input.bytes().enumerate();
+    while let Some((i, b)) = bytes.next() {
+        match b {
+            b'"' => {
+                let val = match owned {
+                    Some(mut s) => {
+                        s.push_str(&input[start..i]);
+                        Cow::Owned(s)
+                    }
+                    None => Cow::Borrowed(&input[..i]),
+                };
+                return Some((val, &input[i + 1..]));
+            }
+            b'\\' if matches!(input.as_bytes().get(i + 1), Some(b'"' | b'\\' | b']')) => {
+                let s = owned.get_or_insert_with(String::new);
+                s.push_str(&input[start..i]);
+                start = i + 1;
+                bytes.next();
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use simd_json::OwnedValue;
+
+    #[test]
+    fn structured_data() {
+        let r: OwnedValue = parse(
+            r#"[exampleSDID@32473 iut="3" eventSource="App" eventID="1011"][examplePriority@32473 class="high" class="a\"b\]c\\d\x"]"#,
+        )
+        .expect("Failed to parse structured data");
+        assert_eq!(
+            r,
+            simd_json::json!({
+                "exampleSDID@32473": {"iut": "3", "eventSource": "App", "eventID": "1011"},
+                "examplePriority@32473": {"class": ["high", r#"a"b]c\d\x"#]}
+            })
+        );
+        let r: OwnedValue = parse("-").expect("Failed to parse structured data");
+        assert_eq!(r, simd_json::json!({}));
+        let r: OwnedValue = parse("[id]").expect("Failed to parse structured data");
+        assert_eq!(r, simd_json::json!({"id": {}}));
+
+        let (r, msg): (OwnedValue, _) =
+            parse_prefix(r#"[a x="1"] hello world"#).expect("Failed to parse structured data");
+        assert_eq!(r, simd_json::json!({"a": {"x": "1"}}));
+        assert_eq!(msg, "hello world");
+
+        for input in [
+            "",
+            "[]",
+            "[a x=1]",
+            r#"[a x="1]"#,
+            r#"[a  x="1"]"#,
+            r#"[a x="1"]trailing"#,
+            "[a]b",
+            "[aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa]",
+        ] {
+            assert_eq!(parse::<OwnedValue>(input), None, "{input}");
+        }
+    }
+}
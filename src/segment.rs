@@ -0,0 +1,125 @@
+// Copyright 2020-2021, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Splitting an input into segments that reconstruct it
+
+use crate::{Pattern, RawPair};
+
+/// A part of an input, concatenating the `text` of all segments of an
+/// input yields the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segment<'input> {
+    /// text between pairs: seperators as well as fields that don't form a
+    /// pair
+    Separator(&'input str),
+    /// a field that forms a pair
+    Field {
+        /// the key, unquoted
+        key: &'input str,
+        /// the value as it appears in the input, unquoted
+        value: &'input str,
+        /// the whole field including the key seperator and quotes
+        field: &'input str,
+    },
+}
+
+impl<'input> Segment<'input> {
+    /// The text of the segment as it appears in the input
+    #[must_use]
+    pub fn text(&self) -> &'input str {
+        match self {
+            Self::Separator(s) | Self::Field { field: s, .. } => s,
+        }
+    }
+}
+
+impl Pattern {
+    /// Splits the input into the fields that form pairs and the text
+    /// between them, preserving the exact seperators so the input can be
+    /// edited and re-emitted.
+    ///
+    /// Keys and values are returned as they appear in the input without
+    /// being coerced or normalized. The segments borrow the pattern as well
+    /// as the input since positional keys come from the pattern.
+    pub fn segments<'input>(&'input self, input: &'input str) -> Vec<Segment<'input>> {
+        let start = input.as_ptr() as usize;
+        let mut r = Vec::new();
+        let mut end = 0;
+        for RawPair {
+            field, key, val, ..
+        } in self.raw_pairs(input)
+        {
+            let offset = field.as_ptr() as usize - start;
+            if offset > end {
+                r.push(Segment::Separator(&input[end..offset]));
+            }
+            r.push(Segment::Field {
+                key: self.raw_key(key),
+                value: val,
+                field,
+            });
+            end = offset + field.len();
+        }
+        if end < input.len() {
+            r.push(Segment::Separator(&input[end..]));
+        }
+        r
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn segments() {
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_quotes(&['"']);
+        let input = " a=1  bare b=\"x y\" ";
+        let segments = kv.segments(input);
+        assert_eq!(
+            segments,
+            [
+                Segment::Separator(" "),
+                Segment::Field {
+                    key: "a",
+                    value: "1",
+                    field: "a=1"
+                },
+                Segment::Separator("  bare "),
+                Segment::Field {
+                    key: "b",
+                    value: "x y",
+                    field: "b=\"x y\""
+                },
+                Segment::Separator(" "),
+            ]
+        );
+        assert_eq!(
+            segments.iter().map(Segment::text).collect::<String>(),
+            input
+        );
+
+        let edited: String = segments
+            .iter()
+            .map(|s| match s {
+                Segment::Field { key: "a", .. } => "a=2",
+                s => s.text(),
+            })
+            .collect();
+        assert_eq!(edited, " a=2  bare b=\"x y\" ");
+        assert!(kv.segments("").is_empty());
+    }
+}
@@ -0,0 +1,482 @@
+// Copyright 2020-2021, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Serializing user types into KV text
+
+use crate::Pattern;
+use serde::ser::{self, Impossible, Serialize};
+use std::fmt;
+
+/// An error serializing into KV text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SerializeError(String);
+
+impl fmt::Display for SerializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for SerializeError {}
+
+impl ser::Error for SerializeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+impl From<crate::Error> for SerializeError {
+    fn from(e: crate::Error) -> Self {
+        Self(e.to_string())
+    }
+}
+
+/// Serializes a flat struct or map into a KV string with the seperators of
+/// `pattern`, like `Pattern::encode`.
+///
+/// Fields that are `None` are skipped, sequences are joined with the value
+/// array seperator and unit enum variants are written as their name.
+///
+/// # Errors
+/// if `value` is no struct or map, or has keys or values that can not be
+/// encoded, e.g. nested structs
+pub fn to_string<T>(pattern: &Pattern, value: &T) -> Result<String, SerializeError>
+where
+    T: Serialize + ?Sized,
+{
+    let mut out = String::new();
+    value.serialize(Pairs {
+        pattern,
+        out: &mut out,
+        key: None,
+    })?;
+    Ok(out)
+}
+
+fn unsupported<T>(what: &str) -> Result<T, SerializeError> {
+    Err(SerializeError(format!(
+        "{what} can not be serialized as KV"
+    )))
+}
+
+/// Writes the fields of a struct or the entries of a map as pairs
+struct Pairs<'p, 'o> {
+    pattern: &'p Pattern,
+    out: &'o mut String,
+    /// the key of a map entry whose value is next
+    key: Option<String>,
+}
+
+impl Pairs<'_, '_> {
+    fn pair<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) -> Result<(), SerializeError> {
+        if let Some(val) = value.serialize(Text(self.pattern))? {
+            self.pattern.push_pair(self.out, key, &val)?;
+        }
+        Ok(())
+    }
+}
+
+macro_rules! top_level_unsupported {
+    ($($method:ident($($arg:ty),*))*) => {
+        $(
+            fn $method(self, $(_: $arg),*) -> Result<Self::Ok, Self::Error> {
+                unsupported("only structs and maps are serialized, a scalar")
+            }
+        )*
+    };
+}
+
+impl ser::Serializer for Pairs<'_, '_> {
+    type Ok = ();
+    type Error = SerializeError;
+    type SerializeSeq = Impossible<(), SerializeError>;
+    type SerializeTuple = Impossible<(), SerializeError>;
+    type SerializeTupleStruct = Impossible<(), SerializeError>;
+    type SerializeTupleVariant = Impossible<(), SerializeError>;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Impossible<(), SerializeError>;
+
+    top_level_unsupported! {
+        serialize_bool(bool) serialize_i8(i8) serialize_i16(i16) serialize_i32(i32)
+        serialize_i64(i64) serialize_u8(u8) serialize_u16(u16) serialize_u32(u32)
+        serialize_u64(u64) serialize_f32(f32) serialize_f64(f64) serialize_char(char)
+        serialize_str(&str) serialize_bytes(&[u8]) serialize_none() serialize_unit()
+        serialize_unit_struct(&'static str)
+        serialize_unit_variant(&'static str, u32, &'static str)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), SerializeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<(), SerializeError> {
+        unsupported("an enum variant")
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, SerializeError> {
+        unsupported("only structs and maps are serialized, a sequence")
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, SerializeError> {
+        unsupported("only structs and maps are serialized, a tuple")
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, SerializeError> {
+        unsupported("only structs and maps are serialized, a tuple")
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, SerializeError> {
+        unsupported("an enum variant")
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self, SerializeError> {
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self, SerializeError> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, SerializeError> {
+        unsupported("an enum variant")
+    }
+}
+
+impl ser::SerializeStruct for Pairs<'_, '_> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        self.pair(key, value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for Pairs<'_, '_> {
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerializeError> {
+        match key.serialize(Text(self.pattern))? {
+            Some(key) => self.key = Some(key),
+            None => return unsupported("a missing key"),
+        }
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        let key = self.key.take().unwrap_or_default();
+        self.pair(&key, value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        Ok(())
+    }
+}
+
+/// Turns a key or value into its text, `None` for values that are skipped
+struct Text<'p>(&'p Pattern);
+
+macro_rules! serialize_display {
+    ($($method:ident($ty:ty))*) => {
+        $(
+            fn $method(self, v: $ty) -> Result<Self::Ok, Self::Error> {
+                Ok(Some(v.to_string()))
+            }
+        )*
+    };
+}
+
+impl<'p> ser::Serializer for Text<'p> {
+    type Ok = Option<String>;
+    type Error = SerializeError;
+    type SerializeSeq = Joined<'p>;
+    type SerializeTuple = Joined<'p>;
+    type SerializeTupleStruct = Joined<'p>;
+    type SerializeTupleVariant = Impossible<Option<String>, SerializeError>;
+    type SerializeMap = Impossible<Option<String>, SerializeError>;
+    type SerializeStruct = Impossible<Option<String>, SerializeError>;
+    type SerializeStructVariant = Impossible<Option<String>, SerializeError>;
+
+    serialize_display! {
+        serialize_bool(bool) serialize_i8(i8) serialize_i16(i16) serialize_i32(i32)
+        serialize_i64(i64) serialize_u8(u8) serialize_u16(u16) serialize_u32(u32)
+        serialize_u64(u64) serialize_f32(f32) serialize_f64(f64) serialize_char(char)
+        serialize_str(&str)
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Self::Ok, SerializeError> {
+        unsupported("bytes")
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, SerializeError> {
+        Ok(None)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Self::Ok, SerializeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, SerializeError> {
+        Ok(Some("null".to_string()))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, SerializeError> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, SerializeError> {
+        Ok(Some(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, SerializeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Self::Ok, SerializeError> {
+        unsupported("a nested enum variant")
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Joined<'p>, SerializeError> {
+        let Some(sep) = self.0.value_array_separator.as_deref() else {
+            return unsupported("a sequence without a value array seperator");
+        };
+        Ok(Joined {
+            pattern: self.0,
+            sep,
+            vals: Vec::with_capacity(len.unwrap_or_default()),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Joined<'p>, SerializeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Joined<'p>, SerializeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, SerializeError> {
+        unsupported("a nested enum variant")
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, SerializeError> {
+        unsupported("a nested map")
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, SerializeError> {
+        unsupported("a nested struct")
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, SerializeError> {
+        unsupported("a nested enum variant")
+    }
+}
+
+/// The elements of a sequence joined with the value array seperator
+struct Joined<'p> {
+    pattern: &'p Pattern,
+    sep: &'p str,
+    vals: Vec<String>,
+}
+
+impl Joined<'_> {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        let val = value.serialize(Text(self.pattern))?.unwrap_or_default();
+        if val.contains(self.sep) {
+            return Err(crate::Error::Unencodable(val).into());
+        }
+        self.vals.push(val);
+        Ok(())
+    }
+
+    fn join(self) -> String {
+        self.vals.join(self.sep)
+    }
+}
+
+impl ser::SerializeSeq for Joined<'_> {
+    type Ok = Option<String>;
+    type Error = SerializeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, SerializeError> {
+        Ok(Some(self.join()))
+    }
+}
+
+impl ser::SerializeTuple for Joined<'_> {
+    type Ok = Option<String>;
+    type Error = SerializeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, SerializeError> {
+        Ok(Some(self.join()))
+    }
+}
+
+impl ser::SerializeTupleStruct for Joined<'_> {
+    type Ok = Option<String>;
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, SerializeError> {
+        Ok(Some(self.join()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    enum Level {
+        Info,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Record {
+        msg: String,
+        port: u16,
+        ok: bool,
+        level: Level,
+        user: Option<String>,
+        tags: Vec<String>,
+    }
+
+    #[test]
+    fn to_string() {
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_quotes(&['"'])
+            .with_value_array_separator(",");
+        let r = Record {
+            msg: "hello world".to_string(),
+            port: 80,
+            ok: true,
+            level: Level::Info,
+            user: None,
+            tags: vec!["a".to_string(), "b".to_string()],
+        };
+        let s = super::to_string(&kv, &r).expect("Failed to serialize");
+        assert_eq!(s, "msg=\"hello world\" port=80 ok=true level=info tags=a,b");
+        let back: Record = crate::from_str(&kv, &s).expect("Failed to deserialize");
+        assert_eq!(back, r);
+
+        let m: BTreeMap<_, _> = [("b", 2), ("a", 1)].into_iter().collect();
+        assert_eq!(super::to_string(&kv, &m), Ok("a=1 b=2".to_string()));
+
+        assert!(super::to_string(&kv, &1).is_err());
+        let nested: BTreeMap<_, _> = [("n", BTreeMap::from([("x", 1)]))].into_iter().collect();
+        assert_eq!(
+            super::to_string(&kv, &nested),
+            Err(SerializeError(
+                "a nested map can not be serialized as KV".to_string()
+            ))
+        );
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        assert!(super::to_string(&kv, &r).is_err());
+    }
+}
@@ -0,0 +1,260 @@
+// Copyright 2020-2021, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Incremental splitting of records that arrive in chunks, e.g. from a
+//! socket, without buffering whole messages upstream.
+
+use crate::{Error, Limit, Output, Pattern};
+
+/// Splits records terminated by the pattern's record separator out of
+/// chunks of input as they are fed, holding on to the trailing partial
+/// record until it is terminated by a later chunk.
+///
+/// The partial record is bounded by `Pattern::with_max_input_length`, or
+/// by `DEFAULT_MAX_BUFFERED` bytes if the pattern has no limit, once it
+/// grows past the limit `feed` reports `Error::LimitExceeded` for it and
+/// skips input up to the next record separator.
+#[derive(Debug, Clone)]
+pub struct Parser {
+    pattern: Pattern,
+    buffer: Vec<u8>,
+    skipping: bool,
+    max_buffered: usize,
+}
+
+/// The default limit of the bytes a `Parser` buffers for a record that is
+/// not terminated yet, for patterns without a maximal input length
+pub const DEFAULT_MAX_BUFFERED: usize = 1 << 20;
+
+impl Parser {
+    /// Creates a parser that splits each record with `pattern`
+    #[must_use]
+    pub fn new(pattern: Pattern) -> Self {
+        Self {
+            pattern,
+            buffer: Vec::new(),
+            skipping: false,
+            max_buffered: DEFAULT_MAX_BUFFERED,
+        }
+    }
+
+    /// Limits the bytes buffered for a record that is not terminated yet
+    /// when the pattern has no maximal input length, it defaults to
+    /// `DEFAULT_MAX_BUFFERED`
+    #[must_use]
+    pub fn with_max_buffered(mut self, max: usize) -> Self {
+        self.max_buffered = max;
+        self
+    }
+
+    /// The pattern records are split with
+    #[must_use]
+    pub fn pattern(&self) -> &Pattern {
+        &self.pattern
+    }
+
+    /// The number of bytes of the record that is not terminated yet
+    #[must_use]
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Adds a chunk of input and splits all records it terminates like
+    /// `Pattern::run_bytes`, in order. Chunks may end anywhere, also in the
+    /// middle of a field, a separator or a UTF-8 sequence.
+    pub fn feed<V>(&mut self, chunk: impl AsRef<[u8]>) -> Vec<Result<Option<V>, Error>>
+    where
+        V: for<'input> Output<'input>,
+    {
+        let sep = self.pattern.record_separator.as_bytes();
+        let mut start = self
+            .buffer
+            .len()
+            .saturating_sub(sep.len().saturating_sub(1));
+        self.buffer.extend_from_slice(chunk.as_ref());
+        let mut records = Vec::new();
+        let mut record_start = 0;
+        while let Some(i) = find(&self.buffer[start..], sep) {
+            let end = start + i;
+            if self.skipping {
+                self.skipping = false;
+            } else {
+                records.push(self.split(&self.buffer[record_start..end]));
+            }
+            record_start = end + sep.len();
+            start = record_start;
+        }
+        self.buffer.drain(..record_start);
+        let max = self.pattern.max_input_length.unwrap_or(self.max_buffered);
+        if !self.skipping && self.buffer.len() > max {
+            records.push(Err(Error::LimitExceeded(Limit::InputLength(max))));
+            self.skipping = true;
+        }
+        if self.skipping {
+            // only what could be the start of a separator is kept
+            let keep = sep.len().saturating_sub(1);
+            self.buffer.drain(..self.buffer.len().saturating_sub(keep));
+        }
+        records
+    }
+
+    /// Ends the input and splits the record that was not terminated, if
+    /// there is one.
+    pub fn finish<V>(self) -> Option<Result<Option<V>, Error>>
+    where
+        V: for<'input> Output<'input>,
+    {
+        (!self.skipping && !self.buffer.is_empty()).then(|| self.split(&self.buffer))
+    }
+
+    fn split<V>(&self, record: &[u8]) -> Result<Option<V>, Error>
+    where
+        V: for<'input> Output<'input>,
+    {
+        let record = if self.pattern.record_separator == "\n" {
+            record.strip_suffix(b"\r").unwrap_or(record)
+        } else {
+            record
+        };
+        self.pattern.run_bytes(record)
+    }
+}
+
+/// With an empty separator the whole input is one record so it is never
+/// found
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.is_empty() {
+        return None;
+    }
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use simd_json::OwnedValue;
+
+    #[test]
+    fn feed() {
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        let mut p = Parser::new(kv);
+        assert_eq!(p.feed::<OwnedValue>("a=1 b"), vec![]);
+        assert_eq!(p.buffered(), 5);
+        assert_eq!(
+            p.feed::<OwnedValue>(b"=2\r\nc=3\ngarbage\nd=\xc3"),
+            vec![
+                Ok(Some(simd_json::json!({"a": "1", "b": "2"}))),
+                Ok(Some(simd_json::json!({"c": "3"}))),
+                Ok(None)
+            ]
+        );
+        assert_eq!(p.feed::<OwnedValue>([0xa4]), vec![]);
+        assert_eq!(
+            p.finish::<OwnedValue>(),
+            Some(Ok(Some(simd_json::json!({"d": "ä"}))))
+        );
+
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_record_separator("||");
+        let mut p = Parser::new(kv);
+        assert_eq!(p.feed::<OwnedValue>("a=1|"), vec![]);
+        assert_eq!(
+            p.feed::<OwnedValue>("|b=2||"),
+            vec![
+                Ok(Some(simd_json::json!({"a": "1"}))),
+                Ok(Some(simd_json::json!({"b": "2"})))
+            ]
+        );
+        assert_eq!(p.finish::<OwnedValue>(), None);
+    }
+
+    #[test]
+    fn bounded() {
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_max_input_length(8);
+        let mut p = Parser::new(kv.clone());
+        for i in 0..1000 {
+            let r = p.feed::<OwnedValue>("aaaaaaaaaa");
+            if i == 0 {
+                assert_eq!(r, vec![Err(Error::LimitExceeded(Limit::InputLength(8)))]);
+            } else {
+                assert_eq!(r, vec![]);
+            }
+            assert_eq!(p.buffered(), 0);
+        }
+        assert_eq!(
+            p.feed::<OwnedValue>("a\nb=1\nc="),
+            vec![Ok(Some(simd_json::json!({"b": "1"})))]
+        );
+        assert_eq!(
+            p.finish::<OwnedValue>(),
+            Some(Ok(Some(simd_json::json!({"c": ""}))))
+        );
+
+        let mut p = Parser::new(kv.clone());
+        assert_eq!(
+            p.feed::<OwnedValue>("a=1234567\n"),
+            vec![Err(Error::LimitExceeded(Limit::InputLength(8)))]
+        );
+        assert_eq!(
+            p.feed::<OwnedValue>("a=123456789"),
+            vec![Err(Error::LimitExceeded(Limit::InputLength(8)))]
+        );
+        assert_eq!(p.finish::<OwnedValue>(), None);
+
+        let mut p = Parser::new(kv.with_record_separator("||"));
+        assert_eq!(
+            p.feed::<OwnedValue>("aaaaaaaaa|"),
+            vec![Err(Error::LimitExceeded(Limit::InputLength(8)))]
+        );
+        assert_eq!(p.buffered(), 1);
+        assert_eq!(
+            p.feed::<OwnedValue>("|b=2||"),
+            vec![Ok(Some(simd_json::json!({"b": "2"})))]
+        );
+
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        let mut p = Parser::new(kv.with_record_separator("").with_max_input_length(8));
+        assert_eq!(p.feed::<OwnedValue>("a=1\nb=2"), vec![]);
+        assert_eq!(
+            p.feed::<OwnedValue>("c=3"),
+            vec![Err(Error::LimitExceeded(Limit::InputLength(8)))]
+        );
+        assert_eq!(p.buffered(), 0);
+        assert_eq!(p.finish::<OwnedValue>(), None);
+
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        let mut p = Parser::new(kv.clone());
+        let chunk = "a".repeat(DEFAULT_MAX_BUFFERED);
+        assert_eq!(p.feed::<OwnedValue>(&chunk), vec![]);
+        assert_eq!(
+            p.feed::<OwnedValue>("a"),
+            vec![Err(Error::LimitExceeded(Limit::InputLength(
+                DEFAULT_MAX_BUFFERED
+            )))]
+        );
+        assert_eq!(p.buffered(), 0);
+        let mut p = Parser::new(kv).with_max_buffered(4);
+        assert_eq!(
+            p.feed::<OwnedValue>("a=123"),
+            vec![Err(Error::LimitExceeded(Limit::InputLength(4)))]
+        );
+        assert_eq!(
+            p.feed::<OwnedValue>("\nb=2\n"),
+            vec![Ok(Some(simd_json::json!({"b": "2"})))]
+        );
+    }
+}
@@ -0,0 +1,89 @@
+// Copyright 2020-2021, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Suggesting seperators a pattern might be missing
+
+use crate::{Output, Pattern};
+use std::collections::BTreeMap;
+
+impl Pattern {
+    /// Splits a string that represents KV pairs like `run` and suggests
+    /// characters that might be missing field seperators, most frequent
+    /// first.
+    ///
+    /// A character is suggested when it is punctuation that is not part of
+    /// any seperator or quote of the pattern and directly precedes a key,
+    /// that is a run of alphanumeric characters, `_` or `-` followed by a
+    /// key seperator. For `a=1;b=2` with a space as field seperator `;` is
+    /// suggested.
+    pub fn run_with_suggestions<'input, V>(&self, input: &'input str) -> (Option<V>, Vec<char>)
+    where
+        V: Output<'input>,
+    {
+        let mut counts = BTreeMap::new();
+        for ks in self.key_seperators.iter().filter(|ks| !ks.is_empty()) {
+            for (i, _) in input.match_indices(ks.as_str()) {
+                let before = &input[..i];
+                let key_start = before
+                    .trim_end_matches(|c: char| c.is_alphanumeric() || c == '_' || c == '-')
+                    .len();
+                if key_start == i {
+                    continue;
+                }
+                if let Some(c) = before[..key_start].chars().next_back() {
+                    if self.is_candidate(c) {
+                        *counts.entry(c).or_insert(0_usize) += 1;
+                    }
+                }
+            }
+        }
+        let mut suggestions: Vec<_> = counts.into_iter().collect();
+        suggestions.sort_by(|(_, a), (_, b)| b.cmp(a));
+        (
+            self.run(input),
+            suggestions.into_iter().map(|(c, _)| c).collect(),
+        )
+    }
+
+    /// Checks if a character could be a seperator missing in the pattern
+    fn is_candidate(&self, c: char) -> bool {
+        c.is_ascii_punctuation()
+            && !self.quotes.contains(&c)
+            && !self
+                .field_seperators
+                .iter()
+                .chain(self.key_seperators.iter())
+                .any(|s| s.contains(c))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use simd_json::OwnedValue;
+
+    #[test]
+    fn run_with_suggestions() {
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        let (r, suggestions): (Option<OwnedValue>, _) =
+            kv.run_with_suggestions("a=1;b=2;c=3 d=4|e=5 f=x.y");
+        assert_eq!(r, Some(simd_json::json!({"f": "x.y"})));
+        assert_eq!(suggestions, [';', '|']);
+
+        let kv = Pattern::compile("%{key}=%{val};").expect("Failed to build pattern");
+        let (r, suggestions): (Option<OwnedValue>, _) = kv.run_with_suggestions("a=1;b=2");
+        assert_eq!(r, Some(simd_json::json!({"a": "1", "b": "2"})));
+        assert!(suggestions.is_empty());
+    }
+}
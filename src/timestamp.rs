@@ -0,0 +1,57 @@
+// Copyright 2020-2021, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Coercion of timestamps into epoch values
+
+use chrono::DateTime;
+
+/// Parses an RFC 3339 timestamp into milliseconds since the unix epoch
+pub(crate) fn epoch_millis(s: &str) -> Option<i64> {
+    // cheap shape check so plain values don't go through the parser
+    let b = s.as_bytes();
+    if b.len() < 20 || !b[..4].iter().all(u8::is_ascii_digit) || b[4] != b'-' {
+        return None;
+    }
+    DateTime::parse_from_rfc3339(s)
+        .ok()
+        .map(|t| t.timestamp_millis())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Pattern;
+    use simd_json::prelude::*;
+    use simd_json::BorrowedValue;
+
+    #[test]
+    fn coerce_timestamps() {
+        let kv = Pattern::compile("%{key}=%{val}")
+            .expect("Failed to build pattern")
+            .with_coerce_timestamps(true);
+        let r: BorrowedValue = kv
+            .run("ts=2020-01-01T00:00:00Z local=2020-01-01T01:00:00.5+01:00 day=2020-01-01 msg=hello n=1")
+            .expect("Failed to split input");
+        assert_eq!(r["ts"], 1_577_836_800_000_i64);
+        assert_eq!(r["local"], 1_577_836_800_500_i64);
+        assert_eq!(r["day"], "2020-01-01");
+        assert_eq!(r["msg"], "hello");
+        assert_eq!(r["n"].value_type(), ValueType::String);
+
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        let r: BorrowedValue = kv
+            .run("ts=2020-01-01T00:00:00Z")
+            .expect("Failed to split input");
+        assert_eq!(r["ts"], "2020-01-01T00:00:00Z");
+    }
+}
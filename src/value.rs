@@ -0,0 +1,398 @@
+// Copyright 2020-2021, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The intermediate representation parsed pairs are collected in before
+//! they are turned into the output value
+
+use crate::{DuplicatePolicy, ExpectedType, Output};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// A value as it is inserted into the output
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Val<'input> {
+    Str(Cow<'input, str>),
+    I64(i64),
+    F64(f64),
+    Bool(bool),
+    Null,
+    Array(Vec<Val<'input>>),
+    Object(Obj<'input>),
+    /// an array element below an index that was set
+    Missing,
+}
+
+/// Why the values of an input could not be built
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum InvalidValue {
+    /// a value could not be coerced into its expected type or an array has
+    /// gaps that are errors
+    Type,
+    /// values are nested deeper than the maximum total depth
+    Depth,
+}
+
+impl<'input> Val<'input> {
+    /// Coerces a raw value into a number, boolean or null if it looks like
+    /// one
+    pub(crate) fn coerce(s: Cow<'input, str>) -> Self {
+        match &*s {
+            "true" => Self::Bool(true),
+            "false" => Self::Bool(false),
+            "null" => Self::Null,
+            v => {
+                if let Ok(i) = v.parse() {
+                    Self::I64(i)
+                } else if v.bytes().any(|b| b.is_ascii_digit())
+                    && v.bytes().all(|b| {
+                        b.is_ascii_digit() || matches!(b, b'-' | b'+' | b'.' | b'e' | b'E')
+                    })
+                {
+                    match v.parse::<f64>() {
+                        Ok(f) if f.is_finite() => Self::F64(f),
+                        _ => Self::Str(s),
+                    }
+                } else {
+                    Self::Str(s)
+                }
+            }
+        }
+    }
+
+    /// Parses a raw value as the expected type
+    pub(crate) fn expect(s: &str, expected: ExpectedType) -> Option<Self> {
+        match expected {
+            ExpectedType::Integer => s.parse().ok().map(Self::I64),
+            ExpectedType::Float => s
+                .parse()
+                .ok()
+                .filter(|f: &f64| f.is_finite())
+                .map(Self::F64),
+            ExpectedType::Bool => match s {
+                "true" => Some(Self::Bool(true)),
+                "false" => Some(Self::Bool(false)),
+                _ => None,
+            },
+        }
+    }
+
+    /// Turns the value into one that does not borrow the input
+    fn into_owned(self) -> Val<'static> {
+        match self {
+            Self::Str(s) => Val::Str(Cow::Owned(s.into_owned())),
+            Self::I64(i) => Val::I64(i),
+            Self::F64(f) => Val::F64(f),
+            Self::Bool(b) => Val::Bool(b),
+            Self::Null => Val::Null,
+            Self::Array(a) => Val::Array(a.into_iter().map(Val::into_owned).collect()),
+            Self::Object(o) => Val::Object(o.into_owned()),
+            Self::Missing => Val::Missing,
+        }
+    }
+
+    /// Converts the value into a `serde_json` value, numbers that JSON
+    /// can't represent (NaN and infinities) become `null`
+    #[cfg(feature = "serde_json")]
+    pub(crate) fn into_json(self) -> serde_json::Value {
+        use serde_json::Value;
+        match self {
+            Self::Str(s) => Value::String(s.into_owned()),
+            Self::I64(i) => Value::from(i),
+            Self::F64(f) => serde_json::Number::from_f64(f).map_or(Value::Null, Value::Number),
+            Self::Bool(b) => Value::Bool(b),
+            Self::Null | Self::Missing => Value::Null,
+            Self::Array(a) => Value::Array(a.into_iter().map(Val::into_json).collect()),
+            Self::Object(o) => Value::Object(o.into_json()),
+        }
+    }
+
+    /// An empty container for the path segment following it, with array
+    /// indices an array if the segment is an index
+    fn container(next: &str, arrays: bool) -> Self {
+        if arrays && index(next).is_some() {
+            Self::Array(Vec::new())
+        } else {
+            Self::Object(Obj::default())
+        }
+    }
+
+    /// Checks if the value is the container for the path segment following
+    /// it
+    fn fits(&self, next: &str, arrays: bool) -> bool {
+        match self {
+            Self::Array(_) => arrays && index(next).is_some(),
+            Self::Object(_) => !(arrays && index(next).is_some()),
+            _ => false,
+        }
+    }
+
+    /// Inserts a value at a path below this container
+    fn insert_path(
+        &mut self,
+        path: &[Cow<'input, str>],
+        val: Val<'input>,
+        duplicates: DuplicatePolicy,
+        arrays: bool,
+    ) {
+        let Some(i) = (match self {
+            Self::Array(_) => index(&path[0]),
+            _ => None,
+        }) else {
+            if let Self::Object(o) = self {
+                o.insert_path(path, val, duplicates, arrays);
+            }
+            return;
+        };
+        let Self::Array(a) = self else {
+            return;
+        };
+        if a.len() <= i {
+            a.resize(i + 1, Self::Missing);
+        }
+        match &path[1..] {
+            [] => a[i] = val,
+            rest => {
+                let slot = &mut a[i];
+                if !slot.fits(&rest[0], arrays) {
+                    *slot = Self::container(&rest[0], arrays);
+                }
+                slot.insert_path(rest, val, duplicates, arrays);
+            }
+        }
+    }
+
+    /// Checks if the value has array elements below an index that was set
+    fn has_gaps(&self) -> bool {
+        match self {
+            Self::Missing => true,
+            Self::Array(a) => a.iter().any(Self::has_gaps),
+            Self::Object(o) => o.has_gaps(),
+            _ => false,
+        }
+    }
+
+    /// Checks if the value is a number
+    pub(crate) fn is_number(&self) -> bool {
+        matches!(self, Self::I64(_) | Self::F64(_))
+    }
+
+    /// Compares two numeric values, `None` if either is not a number
+    #[allow(clippy::cast_precision_loss)]
+    pub(crate) fn number_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Self::I64(a), Self::I64(b)) => Some(a.cmp(b)),
+            (Self::I64(a), Self::F64(b)) => (*a as f64).partial_cmp(b),
+            (Self::F64(a), Self::I64(b)) => a.partial_cmp(&(*b as f64)),
+            (Self::F64(a), Self::F64(b)) => a.partial_cmp(b),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn into_value<V: Output<'input>>(self) -> Option<V> {
+        Some(match self {
+            Self::Str(s) => V::from(s),
+            Self::I64(i) => V::from(i),
+            Self::F64(f) => V::from(f),
+            Self::Bool(b) => V::from(b),
+            Self::Null | Self::Missing => V::null(),
+            Self::Object(o) => o.into_value()?,
+            Self::Array(a) => {
+                let mut r = V::array_with_capacity(a.len());
+                for v in a {
+                    r.push(v.into_value::<V>()?).ok()?;
+                }
+                r
+            }
+        })
+    }
+}
+
+/// The largest array index a path segment can have, larger numbers are
+/// object keys so a single pair can't allocate a huge array
+const MAX_INDEX: usize = u16::MAX as usize;
+
+/// Parses a path segment as an array index
+fn index(segment: &str) -> Option<usize> {
+    if segment.is_empty() || !segment.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    segment.parse().ok().filter(|i| *i <= MAX_INDEX)
+}
+
+/// An entry of an object, `count` is the number of times the key was
+/// inserted
+#[derive(Debug, Clone, PartialEq)]
+struct Entry<'input> {
+    key: Cow<'input, str>,
+    val: Val<'input>,
+    count: usize,
+}
+
+/// An object that keeps its entries in insertion order
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Obj<'input> {
+    entries: Vec<Entry<'input>>,
+    index: HashMap<Cow<'input, str>, usize>,
+}
+
+impl<'input> Obj<'input> {
+    pub(crate) fn with_capacity(capacity: usize) -> Self {
+        Self {
+            entries: Vec::with_capacity(capacity),
+            index: HashMap::with_capacity(capacity),
+        }
+    }
+
+    /// Inserts a value, repeated keys are handled according to the policy
+    pub(crate) fn insert(
+        &mut self,
+        key: Cow<'input, str>,
+        val: Val<'input>,
+        duplicates: DuplicatePolicy,
+    ) {
+        if let Some(&i) = self.index.get(&key) {
+            let entry = &mut self.entries[i];
+            match duplicates {
+                DuplicatePolicy::Overwrite => entry.val = val,
+                DuplicatePolicy::Max => {
+                    if entry.val.number_cmp(&val) != Some(Ordering::Greater) {
+                        entry.val = val;
+                    }
+                }
+                DuplicatePolicy::Min => {
+                    if entry.val.number_cmp(&val) != Some(Ordering::Less) {
+                        entry.val = val;
+                    }
+                }
+                DuplicatePolicy::Array => {
+                    if entry.count == 1 {
+                        let first = std::mem::replace(&mut entry.val, Val::Array(Vec::new()));
+                        entry.val = Val::Array(vec![first]);
+                    }
+                    if let Val::Array(a) = &mut entry.val {
+                        a.push(val);
+                    }
+                }
+            }
+            entry.count += 1;
+        } else {
+            self.index.insert(key.clone(), self.entries.len());
+            self.entries.push(Entry { key, val, count: 1 });
+        }
+    }
+
+    /// Inserts a value at a path of keys, missing objects (and arrays for
+    /// index segments with `arrays`) on the path are created and values on
+    /// the path that are not containers of the right kind are replaced
+    pub(crate) fn insert_path(
+        &mut self,
+        path: &[Cow<'input, str>],
+        val: Val<'input>,
+        duplicates: DuplicatePolicy,
+        arrays: bool,
+    ) {
+        match path {
+            [] => (),
+            [key] => self.insert(key.clone(), val, duplicates),
+            [key, rest @ ..] => {
+                let i = if let Some(&i) = self.index.get(key) {
+                    let entry = &mut self.entries[i];
+                    if !entry.val.fits(&rest[0], arrays) {
+                        entry.val = Val::container(&rest[0], arrays);
+                    }
+                    i
+                } else {
+                    self.index.insert(key.clone(), self.entries.len());
+                    self.entries.push(Entry {
+                        key: key.clone(),
+                        val: Val::container(&rest[0], arrays),
+                        count: 1,
+                    });
+                    self.entries.len() - 1
+                };
+                self.entries[i]
+                    .val
+                    .insert_path(rest, val, duplicates, arrays);
+            }
+        }
+    }
+
+    /// Checks if the object has array elements below an index that was set
+    pub(crate) fn has_gaps(&self) -> bool {
+        self.entries.iter().any(|e| e.val.has_gaps())
+    }
+
+    /// Turns the object into one that does not borrow the input
+    pub(crate) fn into_owned(self) -> Obj<'static> {
+        let mut r = Obj::with_capacity(self.entries.len());
+        for Entry { key, val, count } in self.entries {
+            let key: Cow<'static, str> = Cow::Owned(key.into_owned());
+            r.index.insert(key.clone(), r.entries.len());
+            r.entries.push(Entry {
+                key,
+                val: val.into_owned(),
+                count,
+            });
+        }
+        r
+    }
+
+    /// Converts the object into a map that keeps the order of the entries
+    #[cfg(feature = "indexmap")]
+    pub(crate) fn into_index_map<V: Output<'input>>(
+        self,
+    ) -> Option<indexmap::IndexMap<Cow<'input, str>, V>> {
+        self.entries
+            .into_iter()
+            .map(|Entry { key, val, .. }| Some((key, val.into_value::<V>()?)))
+            .collect()
+    }
+
+    /// Converts the object into a `serde_json` map
+    #[cfg(feature = "serde_json")]
+    pub(crate) fn into_json(self) -> serde_json::Map<String, serde_json::Value> {
+        self.entries
+            .into_iter()
+            .map(|Entry { key, val, .. }| (key.into_owned(), val.into_json()))
+            .collect()
+    }
+
+    /// Inserts the entries into an existing object, returns the number of
+    /// entries or `None` if `target` is no object
+    pub(crate) fn insert_into<V: Output<'input>>(self, target: &mut V) -> Option<usize> {
+        let n = self.entries.len();
+        for Entry { key, val, .. } in self.entries {
+            target.insert(key, val.into_value::<V>()?).ok()?;
+        }
+        Some(n)
+    }
+
+    /// The keys and values in insertion order
+    pub(crate) fn into_entries(
+        self,
+    ) -> impl ExactSizeIterator<Item = (Cow<'input, str>, Val<'input>)> {
+        self.entries
+            .into_iter()
+            .map(|Entry { key, val, .. }| (key, val))
+    }
+
+    pub(crate) fn into_value<V: Output<'input>>(self) -> Option<V> {
+        let mut r = V::object_with_capacity(self.entries.len());
+        for Entry { key, val, .. } in self.entries {
+            r.insert(key, val.into_value::<V>()?).ok()?;
+        }
+        Some(r)
+    }
+}
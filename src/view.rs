@@ -0,0 +1,104 @@
+// Copyright 2020-2021, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Lazy views over an input for repeated lookups
+
+use crate::{Pair, Pattern};
+use std::cell::OnceCell;
+
+/// A view of an input that is split the first time it is queried, later
+/// queries reuse the pairs found then.
+#[derive(Debug, Clone)]
+pub struct PatternView<'a> {
+    pattern: &'a Pattern,
+    input: &'a str,
+    pairs: OnceCell<Vec<Pair<'a>>>,
+}
+
+impl<'a> PatternView<'a> {
+    pub(crate) fn new(pattern: &'a Pattern, input: &'a str) -> Self {
+        Self {
+            pattern,
+            input,
+            pairs: OnceCell::new(),
+        }
+    }
+
+    fn pairs(&self) -> &[Pair<'a>] {
+        self.pairs.get_or_init(|| self.pattern.extract(self.input))
+    }
+
+    /// Returns the value of a key, if the key is repeated this is the last
+    /// value as with `Pattern::run`
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.pairs()
+            .iter()
+            .rev()
+            .find_map(|p| (p.key == key).then_some(&*p.val))
+    }
+
+    /// Checks if the input contains the key
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.pairs().iter().any(|p| p.key == key)
+    }
+
+    /// The number of pairs in the input, including repeated keys
+    pub fn len(&self) -> usize {
+        self.pairs().len()
+    }
+
+    /// Checks if the input contains no pairs
+    pub fn is_empty(&self) -> bool {
+        self.pairs().is_empty()
+    }
+
+    /// The input this is a view of
+    pub fn input(&self) -> &'a str {
+        self.input
+    }
+}
+
+impl Pattern {
+    /// Creates a lazy view of the input, splitting is deferred until the
+    /// first query and shared by all later ones.
+    pub fn view<'a>(&'a self, input: &'a str) -> PatternView<'a> {
+        PatternView::new(self, input)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn view() {
+        let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+        let view = kv.view("host=a port=80 host=b path=/");
+        assert!(view.pairs.get().is_none());
+        assert_eq!(view.get("port"), Some("80"));
+        let cached = view.pairs.get().map(Vec::as_ptr);
+        assert!(cached.is_some());
+        assert_eq!(view.get("host"), Some("b"));
+        assert_eq!(view.get("path"), Some("/"));
+        assert_eq!(view.get("nope"), None);
+        assert!(view.contains_key("host"));
+        assert!(!view.contains_key("nope"));
+        assert_eq!(view.len(), 4);
+        assert_eq!(view.pairs.get().map(Vec::as_ptr), cached);
+
+        let view = kv.view("nothing here");
+        assert!(view.is_empty());
+        assert_eq!(view.get("nothing"), None);
+    }
+}
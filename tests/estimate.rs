@@ -0,0 +1,60 @@
+// Copyright 2020-2021, The Tremor Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Checks `Pattern::estimate_bytes` against the allocations `run` makes,
+//! this is its own test binary so the counting allocator only applies here.
+
+#![cfg(feature = "simd-json")]
+
+use simd_json::OwnedValue;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use tremor_kv::Pattern;
+
+/// Counts the bytes allocated by the current thread
+struct Counting;
+
+thread_local! {
+    static ALLOCATED: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.with(|a| a.set(a.get() + layout.size()));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+#[test]
+fn estimate_bytes() {
+    let kv = Pattern::compile("%{key}=%{val}").expect("Failed to build pattern");
+    let input = (0..64)
+        .map(|i| format!("key{i}=value{i}"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let estimate = kv.estimate_bytes(&input);
+    let before = ALLOCATED.with(Cell::get);
+    let r = kv.run::<OwnedValue>(&input);
+    let actual = ALLOCATED.with(Cell::get) - before;
+    assert!(r.is_some());
+    assert!(estimate * 2 >= actual && estimate <= actual * 2);
+    assert_eq!(kv.estimate_bytes("nothing"), 0);
+}